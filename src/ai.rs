@@ -0,0 +1,138 @@
+use std::{fmt, str::FromStr};
+
+use crate::api::LocalPlayerApi;
+use crate::game_state::GameState;
+use crate::player::PlayerId;
+use crate::policy::ActionSource;
+use crate::strategy::{Strategy, StrategyKind};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// How many ticks pass between two turns of an AI.
+    fn get_interval(&self) -> u64 {
+        match self {
+            Difficulty::Easy => 25,
+            Difficulty::Normal => 5,
+            Difficulty::Hard => 1,
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+/// An AI opponent as chosen with `--ai`.
+#[derive(Clone, Debug)]
+pub struct AiSpec {
+    pub strategy: StrategyKind,
+    pub difficulty: Difficulty,
+}
+
+impl Default for AiSpec {
+    fn default() -> Self {
+        AiSpec {
+            strategy: StrategyKind::Balanced,
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+/// Parses `<strategy>[:<difficulty>]`, e.g. `trader:hard` or `bot:scripts/miner.bot:easy`.
+impl FromStr for AiSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Strategies such as `bot:<path>` contain colons themselves, so only a known difficulty
+        // at the end is split off.
+        let (strategy, difficulty) = match s.rsplit_once(':') {
+            Some((strategy, difficulty)) if difficulty.parse::<Difficulty>().is_ok() => {
+                (strategy, difficulty.parse()?)
+            }
+            _ => (s, Difficulty::default()),
+        };
+        Ok(AiSpec {
+            strategy: strategy.parse()?,
+            difficulty,
+        })
+    }
+}
+
+impl fmt::Display for AiSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.strategy, self.difficulty)
+    }
+}
+
+struct AiPlayer {
+    player: PlayerId,
+    difficulty: Difficulty,
+    strategy: Box<dyn Strategy>,
+}
+
+/// The AI opponents of a game. They play through the same actions as everybody else, submitted
+/// as `ActionSource::Ai` so that they are journaled and replayed with the rest.
+#[derive(Default)]
+pub struct AiPlayers {
+    players: Vec<AiPlayer>,
+}
+
+impl AiPlayers {
+    /// Hands the players from `first` on to one AI each, registering those that do not exist yet.
+    pub fn attach(state: &mut GameState, first: PlayerId, specs: &[AiSpec]) -> Self {
+        let players = specs
+            .iter()
+            .zip(first as usize..)
+            .map(|(spec, index)| {
+                let player = index as PlayerId;
+                while state.players().count() <= index {
+                    state.register_player();
+                }
+                AiPlayer {
+                    player,
+                    difficulty: spec.difficulty,
+                    strategy: spec.strategy.build(),
+                }
+            })
+            .collect();
+        AiPlayers { players }
+    }
+
+    /// Lets every AI whose turn it is act. Called once before each step.
+    pub fn act(&mut self, state: &mut GameState) {
+        let tick = state.get_tick();
+        for ai in &mut self.players {
+            // Offset by player so that AIs of the same difficulty do not all act on one tick.
+            if (tick + ai.player as u64).is_multiple_of(ai.difficulty.get_interval()) {
+                let mut api = LocalPlayerApi::with_source(state, ai.player, ActionSource::Ai);
+                ai.strategy.act(tick, &mut api);
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::bank::LoanId;
+use crate::building::{BuildingKind, ProductionId};
+use crate::buy::BuyItem;
+use crate::deal::{Contract, Deal, DealId, TradeOffer};
+use crate::game_state::{ActionError, ActionOutcome, DelayedResult, GameAction, GameState};
+use crate::market::{OrderId, OrderRequest};
+use crate::player::PlayerId;
+use crate::policy::ActionSource;
+use crate::production::ProductionItem;
+use crate::protocol::{
+    BuildingSnapshot, GovernmentSnapshot, LoanSnapshot, MarketSnapshot, OfferSnapshot,
+    OrderSnapshot, PlayerSnapshot, ProductionEntry, ResourceQuantity, TradeSnapshot,
+};
+use crate::resource::Resource;
+use crate::rules::Rule;
+use crate::sell::SellItem;
+
+/// Everything a player can observe and do, independent of how the game is being presented.
+/// Bots should be written against this trait rather than against `GameState` directly.
+pub trait PlayerApi {
+    /// The id of the player this API acts on behalf of.
+    fn get_player_id(&self) -> PlayerId;
+
+    /// The full public state of the player.
+    fn get_player(&self) -> PlayerSnapshot;
+
+    /// What the consumer sector currently pays for each sellable item.
+    fn get_trades(&self) -> Vec<TradeSnapshot>;
+
+    /// What the producer sector currently asks for each buyable item.
+    fn get_offers(&self) -> Vec<OfferSnapshot>;
+
+    /// The order book of every resource.
+    fn get_markets(&self) -> Vec<MarketSnapshot>;
+
+    /// The taxes the government levies, what it has raised and its bids for public works.
+    fn get_government(&self) -> GovernmentSnapshot;
+
+    /// Performs an action on behalf of the player, subject to the game's API restrictions.
+    fn submit(&mut self, action: GameAction) -> Result<ActionOutcome, ActionError>;
+
+    /// What became of the player's delayed actions applied since the last call, oldest first.
+    fn take_delayed_results(&mut self) -> Vec<DelayedResult>;
+
+    fn get_money(&self) -> u64 {
+        self.get_player().money
+    }
+
+    fn get_stockpile(&self) -> Vec<ResourceQuantity> {
+        self.get_player().stockpile
+    }
+
+    /// The number of workers gathering each resource. Workers not listed are idle.
+    fn get_workers(&self) -> Vec<ResourceQuantity> {
+        self.get_player().gathering_workers
+    }
+
+    fn get_idle_workers(&self) -> u32 {
+        self.get_player().idle_workers
+    }
+
+    /// Queued production in all buildings, building by building, with the item each one is
+    /// currently producing first.
+    fn get_production_queue(&self) -> Vec<ProductionEntry> {
+        self.get_player().production_queue
+    }
+
+    /// The player's buildings, finished or not, with their staff and production queues.
+    fn get_buildings(&self) -> Vec<BuildingSnapshot> {
+        self.get_player().buildings
+    }
+
+    /// The player's automation rules, in the order they are evaluated.
+    fn get_rules(&self) -> Vec<Rule> {
+        self.get_player().rules
+    }
+
+    /// The trade offers the player made or received and has not yet settled.
+    fn get_trade_offers(&self) -> Vec<TradeOffer> {
+        self.get_player().trade_offers
+    }
+
+    /// The running contracts the player supplies or buys under.
+    fn get_contracts(&self) -> Vec<Contract> {
+        self.get_player().contracts
+    }
+
+    /// The player's outstanding loans, oldest first.
+    fn get_loans(&self) -> Vec<LoanSnapshot> {
+        self.get_player().loans
+    }
+
+    /// The player's orders currently resting in the market.
+    fn get_orders(&self) -> Vec<OrderSnapshot> {
+        self.get_player().orders
+    }
+
+    fn allocate_worker(&mut self, resource: Resource) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::AllocateWorker(player, resource))
+    }
+
+    fn deallocate_worker(&mut self, resource: Resource) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::DeallocateWorker(player, resource))
+    }
+
+    /// Pays the cost of `item` from the stockpile and appends it to the production queue of the
+    /// finished building of its kind with the least production queued.
+    fn produce(&mut self, item: ProductionItem) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::Produce(player, item))
+    }
+
+    /// Takes queued item `id` out of production and refunds its cost to the stockpile.
+    fn cancel_production(&mut self, id: ProductionId) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::CancelProduction(player, id))
+    }
+
+    /// Moves queued item `id` to `position` in the queue of its building, 0 being the front.
+    fn move_production(
+        &mut self,
+        id: ProductionId,
+        position: usize,
+    ) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::MoveProduction(player, id, position))
+    }
+
+    /// Sets whether queued item `id` is paid for and queued again every time it is finished.
+    fn repeat_production(
+        &mut self,
+        id: ProductionId,
+        repeat: bool,
+    ) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::RepeatProduction(player, id, repeat))
+    }
+
+    /// Sells one batch of `item` to the best bids in the market.
+    fn sell(&mut self, item: SellItem) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::Sell(player, item))
+    }
+
+    /// Buys one batch of `item` from the best asks in the market.
+    fn buy(&mut self, item: BuyItem) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::Buy(player, item))
+    }
+
+    /// Places a limit order. Money for bids and resources for asks are held by the market until
+    /// the order fills or is cancelled.
+    fn place_order(&mut self, request: OrderRequest) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::PlaceOrder(player, request))
+    }
+
+    fn cancel_order(&mut self, id: OrderId) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::CancelOrder(player, id))
+    }
+
+    /// Pays for an exploration that finds a new deposit of `resource` after a while.
+    fn explore(&mut self, resource: Resource) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::Explore(player, resource))
+    }
+
+    /// Pays the cost of a building of `kind` from the stockpile and starts constructing it.
+    fn build(&mut self, kind: BuildingKind) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::Build(player, kind))
+    }
+
+    /// Moves an idle worker into the finished building of `kind` with the fewest staff.
+    fn staff_building(&mut self, kind: BuildingKind) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::StaffBuilding(player, kind))
+    }
+
+    /// Makes a worker idle from the building of `kind` with the most staff.
+    fn unstaff_building(&mut self, kind: BuildingKind) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::UnstaffBuilding(player, kind))
+    }
+
+    /// Adds `rule` after the player's other rules. From the next tick on the game takes its action
+    /// whenever its condition holds, as if it was submitted through the API.
+    fn add_rule(&mut self, rule: Rule) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::AddRule(player, rule))
+    }
+
+    fn remove_rule(&mut self, index: usize) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::RemoveRule(player, index))
+    }
+
+    /// Switches rule `index` on or off.
+    fn toggle_rule(&mut self, index: usize) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::ToggleRule(player, index))
+    }
+
+    /// Offers `deal` to player `to`. What the player gives in an exchange is held until the offer
+    /// is accepted or declined.
+    fn propose_deal(&mut self, to: PlayerId, deal: Deal) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::ProposeDeal(player, to, deal))
+    }
+
+    /// Accepts offer `id`, completing the exchange or starting the contract.
+    fn accept_deal(&mut self, id: DealId) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::AcceptDeal(player, id))
+    }
+
+    /// Declines an offer received, or withdraws one made.
+    fn decline_deal(&mut self, id: DealId) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::DeclineDeal(player, id))
+    }
+
+    /// Declines offer `id` and offers `deal` to its sender instead.
+    fn counter_deal(&mut self, id: DealId, deal: Deal) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::CounterDeal(player, id, deal))
+    }
+
+    /// Borrows `amount` from the bank, to be repaid in equal installments over `periods` upkeep
+    /// periods.
+    fn take_loan(&mut self, amount: u64, periods: u32) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::TakeLoan(player, amount, periods))
+    }
+
+    /// Pays up to `amount` off loan `id` ahead of schedule.
+    fn repay_loan(&mut self, id: LoanId, amount: u64) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::RepayLoan(player, id, amount))
+    }
+
+    fn deposit(&mut self, amount: u64) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::DepositMoney(player, amount))
+    }
+
+    fn withdraw(&mut self, amount: u64) -> Result<ActionOutcome, ActionError> {
+        let player = self.get_player_id();
+        self.submit(GameAction::WithdrawMoney(player, amount))
+    }
+}
+
+/// A `PlayerApi` operating directly on an in-process `GameState`.
+pub struct LocalPlayerApi<'a> {
+    state: &'a mut GameState,
+    player: PlayerId,
+    source: ActionSource,
+}
+
+impl<'a> LocalPlayerApi<'a> {
+    pub fn new(state: &'a mut GameState, player: PlayerId) -> Self {
+        Self::with_source(state, player, ActionSource::Api)
+    }
+
+    /// Submits actions as coming from `source` rather than from the API.
+    pub fn with_source(state: &'a mut GameState, player: PlayerId, source: ActionSource) -> Self {
+        LocalPlayerApi {
+            state,
+            player,
+            source,
+        }
+    }
+}
+
+impl PlayerApi for LocalPlayerApi<'_> {
+    fn get_player_id(&self) -> PlayerId {
+        self.player
+    }
+
+    fn get_player(&self) -> PlayerSnapshot {
+        PlayerSnapshot::new(self.state.get_player(self.player), self.state)
+    }
+
+    fn get_offers(&self) -> Vec<OfferSnapshot> {
+        OfferSnapshot::current(self.state)
+    }
+
+    fn get_markets(&self) -> Vec<MarketSnapshot> {
+        MarketSnapshot::current(self.state)
+    }
+
+    fn get_trades(&self) -> Vec<TradeSnapshot> {
+        TradeSnapshot::current(self.state)
+    }
+
+    fn get_government(&self) -> GovernmentSnapshot {
+        GovernmentSnapshot::new(self.state)
+    }
+
+    fn submit(&mut self, action: GameAction) -> Result<ActionOutcome, ActionError> {
+        if action.get_player() != Some(self.player) {
+            return Err(ActionError::NotPermitted);
+        }
+        self.state.submit_action(action, self.source)
+    }
+
+    fn take_delayed_results(&mut self) -> Vec<DelayedResult> {
+        self.state.take_delayed_results(self.player)
+    }
+}
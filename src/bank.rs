@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use crate::content;
+use crate::npc::NpcItem;
+use crate::resource::{Resource, ResourceAmount};
+use crate::sell::SellItem;
+
+/// Identifies a loan among those of its borrower.
+pub type LoanId = u64;
+
+/// Interest is counted in billionths of money, so that it accrues every tick even on small sums.
+pub const RATE_SCALE: u64 = 1_000_000_000;
+
+/// Adds a tick of interest at `rate` billionths per tick to `balance`, carrying what does not make
+/// a whole unit of money in `accrued`.
+fn accrue(balance: &mut u64, accrued: &mut u64, rate: u64) {
+    let interest = *balance as u128 * rate as u128 + *accrued as u128;
+    *balance = balance.saturating_add((interest / RATE_SCALE as u128) as u64);
+    *accrued = (interest % RATE_SCALE as u128) as u64;
+}
+
+/// What the bank reckons a unit of `resource` to be worth: the starting price the consumer
+/// sector pays for it, or nothing if it does not buy it.
+pub fn unit_value(resource: Resource) -> u64 {
+    enum_iterator::all::<SellItem>()
+        .find(|item| item.get_resource() == resource)
+        .map_or(0, |item| {
+            let trade = content::get().get_sell_trade(item);
+            trade.receive / trade.give.get(resource).max(1) as u64
+        })
+}
+
+/// The value of `stockpile` at the bank's prices.
+pub fn stock_value(stockpile: &ResourceAmount) -> u64 {
+    enum_iterator::all::<Resource>()
+        .map(|res| stockpile.get(res) as u64 * unit_value(res))
+        .sum()
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Loan {
+    pub id: LoanId,
+    /// Money owed, interest included.
+    pub balance: u64,
+    accrued: u64,
+    /// Installments left until the loan is repaid.
+    pub periods_left: u32,
+    /// Installments missed in a row.
+    pub missed: u32,
+}
+
+impl Loan {
+    /// The money due at the next payday: an equal share of the balance for every installment left.
+    pub fn get_installment(&self) -> u64 {
+        self.balance.div_ceil(self.periods_left.max(1) as u64)
+    }
+}
+
+/// A player's standing with the bank: money deposited, loans taken out and past bankruptcies.
+/// Money owed is kept here rather than taken off the player's money, which cannot go negative.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Account {
+    deposit: u64,
+    deposit_accrued: u64,
+    loans: Vec<Loan>,
+    next_loan_id: LoanId,
+    /// The tick before which a bankrupt player may not borrow again.
+    locked_until: u64,
+    bankruptcies: u32,
+}
+
+impl Account {
+    pub fn get_deposit(&self) -> u64 {
+        self.deposit
+    }
+
+    pub fn add_deposit(&mut self, amount: u64) {
+        self.deposit += amount;
+    }
+
+    pub fn withdraw(&mut self, amount: u64) -> bool {
+        if self.deposit < amount {
+            return false;
+        }
+        self.deposit -= amount;
+        true
+    }
+
+    pub fn get_loans(&self) -> impl Iterator<Item = &Loan> {
+        self.loans.iter()
+    }
+
+    /// Everything owed on all loans.
+    pub fn get_debt(&self) -> u64 {
+        self.loans.iter().map(|loan| loan.balance).sum()
+    }
+
+    pub fn get_bankruptcies(&self) -> u32 {
+        self.bankruptcies
+    }
+
+    pub fn is_locked(&self, tick: u64) -> bool {
+        tick < self.locked_until
+    }
+
+    pub fn borrow(&mut self, amount: u64, periods: u32) -> LoanId {
+        let id = self.next_loan_id;
+        self.next_loan_id += 1;
+        self.loans.push(Loan {
+            id,
+            balance: amount,
+            accrued: 0,
+            periods_left: periods,
+            missed: 0,
+        });
+        id
+    }
+
+    /// Pays `amount` off loan `id`, at most its balance, and returns what was paid. A loan paid
+    /// off in full is closed.
+    pub fn repay(&mut self, id: LoanId, amount: u64) -> Option<u64> {
+        let loan = self.loans.iter_mut().find(|loan| loan.id == id)?;
+        let paid = amount.min(loan.balance);
+        loan.balance -= paid;
+        self.loans.retain(|loan| loan.balance > 0);
+        Some(paid)
+    }
+
+    /// Adds a tick of interest to the deposit and to every loan.
+    pub fn accrue_interest(&mut self) {
+        let bank = content::get().get_bank();
+        accrue(
+            &mut self.deposit,
+            &mut self.deposit_accrued,
+            bank.deposit_interest,
+        );
+        for loan in self.loans.iter_mut() {
+            accrue(&mut loan.balance, &mut loan.accrued, bank.loan_interest);
+        }
+    }
+
+    /// Pays the installment of every loan that `money` covers, oldest loan first. Returns whether
+    /// a loan has now missed so many installments that its borrower defaults.
+    pub fn pay_installments(&mut self, money: &mut u64) -> bool {
+        let bank = content::get().get_bank();
+        for loan in self.loans.iter_mut() {
+            let installment = loan.get_installment();
+            if *money >= installment {
+                *money -= installment;
+                loan.balance -= installment;
+                loan.periods_left = loan.periods_left.saturating_sub(1);
+                loan.missed = 0;
+            } else {
+                loan.missed += 1;
+            }
+        }
+        self.loans.retain(|loan| loan.balance > 0);
+        self.loans
+            .iter()
+            .any(|loan| loan.missed >= bank.default_after)
+    }
+
+    /// Closes every loan and keeps the player from borrowing until `locked_until`.
+    pub fn write_off(&mut self, locked_until: u64) {
+        self.loans.clear();
+        self.locked_until = locked_until;
+        self.bankruptcies += 1;
+    }
+}
@@ -0,0 +1,225 @@
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    io::{self, BufWriter, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::api::LocalPlayerApi;
+use crate::game_state::GameState;
+use crate::player::{PlayerId, WorkerAction};
+use crate::policy::ActionPolicy;
+use crate::resource::Resource;
+use crate::strategy::StrategyKind;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StatsFormat {
+    #[default]
+    Csv,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(StatsFormat::Csv),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(format!("unknown stats format {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    pub ticks: u64,
+    pub games: u32,
+    /// Statistics are written every this many ticks.
+    pub every: u64,
+    pub bots: Vec<StrategyKind>,
+    pub format: StatsFormat,
+    /// Where to write the statistics. Standard output if unset.
+    pub output: Option<String>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            ticks: 1000,
+            games: 1,
+            every: 1,
+            bots: Vec::new(),
+            format: StatsFormat::default(),
+            output: None,
+        }
+    }
+}
+
+/// The statistics of one player at one tick of one game.
+#[derive(Serialize)]
+struct PlayerStats {
+    game: u32,
+    seed: u64,
+    tick: u64,
+    player: PlayerId,
+    strategy: String,
+    money: u64,
+    workers: usize,
+    idle_workers: usize,
+    stockpile: BTreeMap<String, u32>,
+    best_bids: BTreeMap<String, Option<u64>>,
+    best_asks: BTreeMap<String, Option<u64>>,
+}
+
+fn csv_header() -> String {
+    let mut header = "game,seed,tick,player,strategy,money,workers,idle_workers".to_owned();
+    for prefix in ["", "bid_", "ask_"] {
+        for res in enum_iterator::all::<Resource>() {
+            let _ = write!(header, ",{}{}", prefix, res);
+        }
+    }
+    header + "\n"
+}
+
+fn write_csv_row(out: &mut String, stats: &PlayerStats) {
+    let _ = write!(
+        out,
+        "{},{},{},{},{},{},{},{}",
+        stats.game,
+        stats.seed,
+        stats.tick,
+        stats.player,
+        stats.strategy,
+        stats.money,
+        stats.workers,
+        stats.idle_workers
+    );
+    for res in Resource::names() {
+        let _ = write!(out, ",{}", stats.stockpile[&res]);
+    }
+    for prices in [&stats.best_bids, &stats.best_asks] {
+        for res in Resource::names() {
+            match prices[&res] {
+                Some(price) => {
+                    let _ = write!(out, ",{}", price);
+                }
+                None => out.push(','),
+            }
+        }
+    }
+    out.push('\n');
+}
+
+fn write_stats(
+    out: &mut String,
+    format: StatsFormat,
+    game: u32,
+    state: &GameState,
+    bots: &[StrategyKind],
+) {
+    let market = state.get_market();
+    let best_bids = enum_iterator::all::<Resource>()
+        .map(|res| (res.to_string(), market.get_book(res).best_bid()))
+        .collect::<BTreeMap<_, _>>();
+    let best_asks = enum_iterator::all::<Resource>()
+        .map(|res| (res.to_string(), market.get_book(res).best_ask()))
+        .collect::<BTreeMap<_, _>>();
+    for (player, strategy) in state.players().zip(bots) {
+        let stats = PlayerStats {
+            game,
+            seed: state.get_seed(),
+            tick: state.get_tick(),
+            player: player.get_id(),
+            strategy: strategy.to_string(),
+            money: player.get_money(),
+            workers: player.workers.len(),
+            idle_workers: player
+                .workers
+                .iter()
+                .filter(|w| w.current_action == WorkerAction::Idle)
+                .count(),
+            stockpile: enum_iterator::all::<Resource>()
+                .map(|res| (res.to_string(), player.get_stockpile().get(res)))
+                .collect(),
+            best_bids: best_bids.clone(),
+            best_asks: best_asks.clone(),
+        };
+        match format {
+            StatsFormat::Csv => write_csv_row(out, &stats),
+            StatsFormat::Json => {
+                out.push_str(&serde_json::to_string(&stats).unwrap());
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Plays one game with a player for every bot and returns its statistics, formatted.
+fn run_game(game: u32, seed: u64, policy: ActionPolicy, options: &BatchOptions) -> String {
+    let mut state = GameState::new(seed);
+    state.set_policy(policy);
+    let players: Vec<PlayerId> = options
+        .bots
+        .iter()
+        .map(|_| state.register_player())
+        .collect();
+    let mut bots: Vec<_> = options.bots.iter().map(|kind| kind.build()).collect();
+
+    let mut out = String::new();
+    for tick in 0..options.ticks {
+        for (player, bot) in players.iter().zip(bots.iter_mut()) {
+            bot.act(tick, &mut LocalPlayerApi::new(&mut state, *player));
+        }
+        state.step();
+        if state.get_tick().is_multiple_of(options.every) {
+            write_stats(&mut out, options.format, game, &state, &options.bots);
+        }
+    }
+    out
+}
+
+/// Plays `options.games` games without a terminal, as fast as possible, spread over all
+/// available cores. Game `i` is seeded with `seed + i`.
+pub fn run_batch(options: &BatchOptions, seed: u64, policy: ActionPolicy) -> io::Result<()> {
+    let writer: Box<dyn Write + Send> = match &options.output {
+        Some(path) => Box::new(fs::File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = BufWriter::new(writer);
+    if options.format == StatsFormat::Csv {
+        writer.write_all(csv_header().as_bytes())?;
+    }
+    let writer = Mutex::new(writer);
+    let next_game = AtomicU32::new(0);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| -> io::Result<()> {
+                    loop {
+                        let game = next_game.fetch_add(1, Ordering::Relaxed);
+                        if game >= options.games {
+                            return Ok(());
+                        }
+                        let stats = run_game(game, seed.wrapping_add(game as u64), policy, options);
+                        writer.lock().unwrap().write_all(stats.as_bytes())?;
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().unwrap())
+    })?;
+    writer.into_inner().unwrap().flush()
+}
@@ -0,0 +1,200 @@
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::content;
+use crate::game_state::Duration;
+use crate::production::ProductionItem;
+use crate::resource::ResourceAmount;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Sequence, Serialize)]
+pub enum BuildingKind {
+    #[default]
+    Headquarters = 0,
+    Mine = 1,
+    Smelter = 2,
+    Workshop = 3,
+    Warehouse = 4,
+}
+
+impl BuildingKind {
+    pub fn get_cost(&self) -> ResourceAmount {
+        content::get().get_building(*self).cost
+    }
+
+    pub fn get_construction_time(&self) -> Duration {
+        content::get().get_building(*self).time
+    }
+
+    /// How many workers can staff a building of this kind.
+    pub fn get_slots(&self) -> u32 {
+        content::get().get_building(*self).slots
+    }
+
+    /// The money a building of this kind costs every pay period.
+    pub fn get_maintenance(&self) -> u64 {
+        content::get().get_building(*self).maintenance
+    }
+
+    /// The capacity a building of this kind adds for every resource.
+    pub fn get_storage(&self) -> u32 {
+        content::get().get_building(*self).storage
+    }
+
+    /// The percentage a building of this kind adds to the yield of every gathering worker.
+    pub fn get_gather_bonus(&self) -> u32 {
+        content::get().get_building(*self).gather_bonus
+    }
+
+    pub fn is_buildable(&self) -> bool {
+        content::get().get_building(*self).buildable
+    }
+}
+
+impl FromStr for BuildingKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_iterator::all::<BuildingKind>()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| format!("unknown building {}", s))
+    }
+}
+
+impl fmt::Display for BuildingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The index of a building among those of its owner. Buildings are never torn down, so it stays
+/// valid for the rest of the game.
+pub type BuildingId = u32;
+
+/// Identifies a queued item among everything its owner has queued.
+pub type ProductionId = u64;
+
+/// An item in the production queue of a building.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct QueuedItem {
+    pub id: ProductionId,
+    pub item: ProductionItem,
+    pub remaining: Duration,
+    /// Whether the item is paid for and queued again every time it is finished.
+    pub repeat: bool,
+}
+
+/// A building a player owns. Each one works through its own production queue.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Building {
+    kind: BuildingKind,
+    /// Ticks of construction left. The building does nothing until it is finished.
+    construction: Duration,
+    /// Whether the last maintenance went unpaid, which stops the building until it is paid.
+    unpaid: bool,
+    queue: VecDeque<QueuedItem>,
+}
+
+impl Building {
+    /// A building that still has to be constructed.
+    pub fn new(kind: BuildingKind) -> Self {
+        Building {
+            kind,
+            construction: kind.get_construction_time(),
+            unpaid: false,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// A building that is ready from the start.
+    pub fn finished(kind: BuildingKind) -> Self {
+        Building {
+            construction: Duration { ticks: 0 },
+            ..Building::new(kind)
+        }
+    }
+
+    pub fn get_kind(&self) -> BuildingKind {
+        self.kind
+    }
+
+    pub fn get_construction(&self) -> Duration {
+        self.construction
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.construction.ticks == 0
+    }
+
+    pub fn is_unpaid(&self) -> bool {
+        self.unpaid
+    }
+
+    pub fn set_unpaid(&mut self, unpaid: bool) {
+        self.unpaid = unpaid;
+    }
+
+    /// Whether the building is finished and maintained, and so does its work.
+    pub fn is_active(&self) -> bool {
+        self.is_finished() && !self.unpaid
+    }
+
+    pub fn enqueue(&mut self, id: ProductionId, item: ProductionItem, repeat: bool) {
+        self.queue.push_back(QueuedItem {
+            id,
+            item,
+            remaining: item.get_production_time(),
+            repeat,
+        });
+    }
+
+    pub fn get_queue(&self) -> impl Iterator<Item = &QueuedItem> {
+        self.queue.iter()
+    }
+
+    pub fn get_queued_mut(&mut self, id: ProductionId) -> Option<&mut QueuedItem> {
+        self.queue.iter_mut().find(|queued| queued.id == id)
+    }
+
+    /// Takes item `id` out of the queue, progress and all.
+    pub fn cancel(&mut self, id: ProductionId) -> Option<QueuedItem> {
+        let index = self.queue.iter().position(|queued| queued.id == id)?;
+        self.queue.remove(index)
+    }
+
+    /// Moves item `id` to `position` in the queue, or to the back if the queue is shorter. An
+    /// item moved away from the front keeps the progress made on it. Returns false if the item is
+    /// not queued here.
+    pub fn move_queued(&mut self, id: ProductionId, position: usize) -> bool {
+        let Some(queued) = self.cancel(id) else {
+            return false;
+        };
+        self.queue.insert(position.min(self.queue.len()), queued);
+        true
+    }
+
+    /// The ticks of production queued, as if nobody staffed the building.
+    pub fn get_backlog(&self) -> u64 {
+        self.queue.iter().map(|queued| queued.remaining.ticks).sum()
+    }
+
+    /// Works for one tick with `staff` workers and returns the item finished, if any. Every
+    /// worker adds as much progress as the building makes on its own.
+    pub fn step(&mut self, staff: u32) -> Option<QueuedItem> {
+        if !self.is_finished() {
+            self.construction.ticks -= 1;
+            return None;
+        }
+        if self.unpaid {
+            return None;
+        }
+        let queued = self.queue.front_mut()?;
+        queued.remaining.ticks = queued.remaining.ticks.saturating_sub(1 + staff as u64);
+        if queued.remaining.ticks == 0 {
+            self.queue.pop_front()
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::content;
+use crate::market::Side;
+use crate::npc::NpcItem;
+use crate::resource::Resource;
+use crate::sell::Trade;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Sequence, Serialize)]
+pub enum BuyItem {
+    Iron = 0,
+    Stone = 1,
+    Copper = 2,
+}
+
+impl NpcItem for BuyItem {
+    const SIDE: Side = Side::Sell;
+
+    fn get_resource(&self) -> Resource {
+        match self {
+            BuyItem::Iron => Resource::Iron,
+            BuyItem::Stone => Resource::Stone,
+            BuyItem::Copper => Resource::Copper,
+        }
+    }
+
+    fn get_default_trade(&self) -> Trade {
+        content::get().get_buy_trade(*self)
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for BuyItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -1,40 +1,167 @@
-use ratatui::{backend::TermionBackend, Terminal};
-use std::{io, io::Read, thread, time::Duration};
+use ratatui::{
+    backend::{Backend, TermionBackend},
+    Terminal,
+};
+use std::{
+    io,
+    io::Read,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
 use termion::screen::IntoAlternateScreen;
 use termion::{async_stdin, input::MouseTerminal, raw::IntoRawMode};
 
+use crate::ai::AiPlayers;
+use crate::api::LocalPlayerApi;
+use crate::clock::{GameClock, GameSpeed};
 use crate::game_state::GameState;
 use crate::input::{parse_input, InputAction};
+use crate::journal::Replay;
 use crate::player::PlayerId;
+use crate::policy::ActionSource;
+use crate::save::save_game;
+use crate::script::ScriptBot;
+use crate::strategy::Strategy;
 use crate::visualization::Visualization;
 
-pub fn run_client(state: &mut GameState, player: PlayerId) {
+const REPLAY_SEEK_TICKS: u64 = 100;
+
+fn open_visualization() -> Visualization<impl Backend> {
     let stdout = io::stdout().into_raw_mode().unwrap();
     let stdout = MouseTerminal::from(stdout);
     let stdout = stdout.into_alternate_screen().unwrap();
     let backend = TermionBackend::new(stdout);
     let terminal = Terminal::new(backend).unwrap();
-    let mut vis = Visualization::new(terminal);
+    Visualization::new(terminal)
+}
+
+/// Lets the AIs and the bot script act, then advances the game by a tick.
+fn act_and_step(
+    state: &mut GameState,
+    player: PlayerId,
+    ais: &mut AiPlayers,
+    bot: &mut Option<ScriptBot>,
+) {
+    ais.act(state);
+    if let Some(bot) = bot {
+        let tick = state.get_tick();
+        bot.act(tick, &mut LocalPlayerApi::new(state, player));
+    }
+    state.step();
+}
 
+/// Runs the interactive client for `player` against `ais`. The quick-save hotkey writes the game
+/// to `save_path`. A `bot` plays alongside the human through the API, every tick the game steps.
+pub fn run_client(
+    state: Arc<Mutex<GameState>>,
+    player: PlayerId,
+    mut ais: AiPlayers,
+    mut bot: Option<ScriptBot>,
+    save_path: String,
+) {
+    let mut vis = open_visualization();
+
+    #[allow(clippy::unbuffered_bytes)]
     let mut stdin = async_stdin().bytes();
 
-    let mut counter = 0;
+    let mut clock = GameClock::new(GameSpeed::Normal);
     'outer: loop {
+        let mut state = state.lock().unwrap();
         while let Some(in_action) = parse_input(&mut stdin) {
             if let Some(game_action) = match in_action {
                 InputAction::Quit => break 'outer,
+                InputAction::SpeedUp => {
+                    clock.set_speed(clock.get_speed().faster());
+                    None
+                }
+                InputAction::SlowDown => {
+                    clock.set_speed(clock.get_speed().slower());
+                    None
+                }
+                InputAction::StepOnce => {
+                    if state.is_paused() {
+                        act_and_step(&mut state, player, &mut ais, &mut bot);
+                    }
+                    None
+                }
+                InputAction::QuickSave => {
+                    match save_game(&state, &save_path) {
+                        Ok(()) => vis.set_message(format!("Saved to {}", save_path)),
+                        Err(e) => vis.set_message(format!("Save failed: {}", e)),
+                    }
+                    None
+                }
                 _ => vis.handle_input(player, in_action),
             } {
-                state.handle_action(game_action);
+                let _ = state.submit_action(game_action, ActionSource::Interactive);
             }
         }
-        if !state.is_paused() {
-            counter = (counter + 1) % 10;
-            if counter == 0 {
-                state.step();
+        clock.advance(state.is_paused(), || {
+            act_and_step(&mut state, player, &mut ais, &mut bot)
+        });
+        vis.set_speed(clock.get_speed());
+        if let Some(bot) = &bot {
+            vis.set_script_log(bot.get_log().cloned().collect());
+        }
+        vis.draw(player, &state);
+        drop(state);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Plays back a recorded session. The game cannot be acted on; pausing, the game speed and
+/// seeking control the playback instead.
+pub fn run_replay(mut replay: Replay) {
+    let mut vis = open_visualization();
+
+    #[allow(clippy::unbuffered_bytes)]
+    let mut stdin = async_stdin().bytes();
+
+    let mut player: PlayerId = 0;
+    let mut playing = true;
+    let mut clock = GameClock::new(GameSpeed::Normal);
+    'outer: loop {
+        while let Some(in_action) = parse_input(&mut stdin) {
+            match in_action {
+                InputAction::Quit => break 'outer,
+                InputAction::TogglePause => playing = !playing,
+                InputAction::SpeedUp => clock.set_speed(clock.get_speed().faster()),
+                InputAction::SlowDown => clock.set_speed(clock.get_speed().slower()),
+                InputAction::StepOnce if !playing => replay.seek(replay.get_tick() + 1),
+                InputAction::SeekBack => {
+                    replay.seek(replay.get_tick().saturating_sub(REPLAY_SEEK_TICKS))
+                }
+                InputAction::SeekForward => replay.seek(replay.get_tick() + REPLAY_SEEK_TICKS),
+                InputAction::NextPlayer => {
+                    let count = replay.get_state().players().count().max(1);
+                    player = (player + 1) % count as PlayerId;
+                }
+                _ => {
+                    // Tabs may still be browsed, but the actions they produce are dropped.
+                    let _ = vis.handle_input(player, in_action);
+                }
             }
         }
-        vis.draw(player, state);
+        let stopped = !playing || replay.is_finished();
+        clock.advance(stopped, || replay.seek(replay.get_tick() + 1));
+        let status = if replay.is_finished() {
+            "Finished"
+        } else if playing {
+            "Playing"
+        } else {
+            "Stopped"
+        };
+        vis.set_message(format!(
+            "Replay: {}, ends at tick {} | Player {}",
+            status,
+            replay.get_end_tick(),
+            player
+        ));
+        vis.set_speed(clock.get_speed());
+        if (player as usize) < replay.get_state().players().count() {
+            vis.draw(player, replay.get_state());
+        }
         thread::sleep(Duration::from_millis(20));
     }
 }
@@ -0,0 +1,117 @@
+use enum_iterator::Sequence;
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::game_state;
+
+/// How far the clock may fall behind before it gives up on catching up, so that a long stall
+/// does not turn into a burst of ticks.
+const MAX_CATCH_UP_TICKS: u64 = 2 * game_state::Duration::TICKS_PER_SEC;
+/// How long a single call may spend stepping at `GameSpeed::Max`, leaving the rest of the frame
+/// for input and drawing.
+const MAX_SPEED_BUDGET: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Sequence)]
+pub enum GameSpeed {
+    Half,
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+    Max,
+}
+
+impl GameSpeed {
+    /// The real time between two ticks, or `None` if the game runs as fast as it can.
+    fn get_tick_length(&self) -> Option<Duration> {
+        let ticks_per_sec = game_state::Duration::TICKS_PER_SEC;
+        let base = Duration::from_millis(1000 / ticks_per_sec);
+        match self {
+            GameSpeed::Half => Some(base * 2),
+            GameSpeed::Normal => Some(base),
+            GameSpeed::Double => Some(base / 2),
+            GameSpeed::Quadruple => Some(base / 4),
+            GameSpeed::Max => None,
+        }
+    }
+
+    pub fn faster(&self) -> Self {
+        self.next().unwrap_or(*self)
+    }
+
+    pub fn slower(&self) -> Self {
+        self.previous().unwrap_or(*self)
+    }
+}
+
+impl fmt::Display for GameSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameSpeed::Half => write!(f, "0.5x"),
+            GameSpeed::Normal => write!(f, "1x"),
+            GameSpeed::Double => write!(f, "2x"),
+            GameSpeed::Quadruple => write!(f, "4x"),
+            GameSpeed::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// Keeps the game stepping at its speed in real time, however often it is polled.
+pub struct GameClock {
+    speed: GameSpeed,
+    last: Instant,
+    behind: Duration,
+}
+
+impl GameClock {
+    pub fn new(speed: GameSpeed) -> Self {
+        GameClock {
+            speed,
+            last: Instant::now(),
+            behind: Duration::ZERO,
+        }
+    }
+
+    pub fn get_speed(&self) -> GameSpeed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: GameSpeed) {
+        self.speed = speed;
+    }
+
+    /// Calls `step` once for every tick that has come due since the last call and returns how
+    /// many there were. Time spent paused is not made up for afterwards.
+    pub fn advance<F: FnMut()>(&mut self, paused: bool, mut step: F) -> u64 {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        if paused {
+            self.behind = Duration::ZERO;
+            return 0;
+        }
+        let mut ticks = 0;
+        match self.speed.get_tick_length() {
+            Some(tick_length) => {
+                self.behind += elapsed;
+                while self.behind >= tick_length {
+                    step();
+                    ticks += 1;
+                    self.behind -= tick_length;
+                    if ticks == MAX_CATCH_UP_TICKS {
+                        self.behind = Duration::ZERO;
+                    }
+                }
+            }
+            None => {
+                while now.elapsed() < MAX_SPEED_BUDGET {
+                    step();
+                    ticks += 1;
+                }
+            }
+        }
+        ticks
+    }
+}
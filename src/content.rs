@@ -0,0 +1,798 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt, fs, io, path::Path, sync::OnceLock};
+
+use crate::bank::RATE_SCALE;
+use crate::building::BuildingKind;
+use crate::buy::BuyItem;
+use crate::game_state::Duration;
+use crate::npc::NpcItem;
+use crate::production::ProductionItem;
+use crate::resource::{Resource, ResourceAmount};
+use crate::sell::{SellItem, Trade};
+
+const BUILTIN: &str = include_str!("../content/default.toml");
+
+static CONTENT: OnceLock<Content> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResourceDef {
+    pub gatherable: bool,
+    /// How much of the resource a player can store without warehouses.
+    pub capacity: u32,
+    /// The percentage of the stock that spoils every minute.
+    pub decay: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Recipe {
+    pub cost: ResourceAmount,
+    pub output: ResourceAmount,
+    pub workers: u32,
+    /// Ticks of experience in each resource given to a worker.
+    pub training: ResourceAmount,
+    /// The kind of building the item is made in.
+    pub building: BuildingKind,
+    pub time: Duration,
+}
+
+/// What a building costs to put up and keep, and what it does. Every player starts with `start`
+/// finished buildings of the kind.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildingDef {
+    pub cost: ResourceAmount,
+    pub time: Duration,
+    pub slots: u32,
+    pub maintenance: u64,
+    /// Capacity added for every resource.
+    pub storage: u32,
+    /// Percentage added to the yield of every gathering worker.
+    pub gather_bonus: u32,
+    pub start: u32,
+    pub buildable: bool,
+}
+
+/// What workers cost to keep and what happens when they are not paid. Missed periods are counted
+/// per worker and reset once the worker is paid again.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Upkeep {
+    pub period: Duration,
+    pub wage: u64,
+    pub idle_wage: u64,
+    pub food: ResourceAmount,
+    pub strike_after: u32,
+    pub desert_after: u32,
+}
+
+/// How workers improve at gathering. `levels` holds the ticks of experience needed for each
+/// level, in ascending order.
+#[derive(Clone, Debug, Default)]
+pub struct Skills {
+    pub levels: Vec<u32>,
+    pub bonus: u32,
+    pub settle_in: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    #[default]
+    Waste,
+    Sell,
+}
+
+/// What happens to resources that do not fit in storage.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Storage {
+    pub overflow: Overflow,
+    pub overflow_price: u32,
+}
+
+/// The size of deposits and what it takes to find new ones.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Deposits {
+    pub start: u32,
+    pub min_yield: u32,
+    pub explore_cost: u64,
+    pub explore_time: Duration,
+    pub min_reserve: u32,
+    pub max_reserve: u32,
+}
+
+/// The terms of the bank. Interest rates are in billionths per tick.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bank {
+    pub loan_interest: u64,
+    pub deposit_interest: u64,
+    pub base_credit: u64,
+    /// The percentage of a player's net worth the bank lends on top of `base_credit`.
+    pub credit_percent: u32,
+    pub max_periods: u32,
+    /// Installments of a loan missed in a row before its borrower goes bankrupt.
+    pub default_after: u32,
+    /// How long a bankrupt player may not borrow.
+    pub lockout: Duration,
+}
+
+/// What the government levies, in percent, and how it spends its revenue: `spending` percent of
+/// the treasury is bid for the resources of public works, split by their weights in `works`, at
+/// `premium` percent above the consumer sector's price.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Government {
+    pub sales_tax: u32,
+    pub transaction_fee: u32,
+    /// Levied every upkeep period on the value of a player's stockpile.
+    pub property_tax: u32,
+    pub spending: u32,
+    pub premium: u32,
+    pub works: ResourceAmount,
+}
+
+/// The balance values of the game: which resources can be gathered, what production items cost
+/// and yield, the starting trades of the NPC sectors, the upkeep and skills of workers and the
+/// deposits they gather from, storage, buildings, the bank and the government.
+///
+/// Content only tunes the entries of the `Resource`, `ProductionItem`, `SellItem`, `BuyItem` and
+/// `BuildingKind` enums: every table holds one entry per variant, and a name in a content file
+/// that is not a variant is rejected as an unknown entry. Adding a resource, item or building
+/// still means adding a variant and rebuilding the game.
+#[derive(Debug, Default)]
+pub struct Content {
+    resources: [ResourceDef; enum_iterator::cardinality::<Resource>()],
+    production: [Recipe; enum_iterator::cardinality::<ProductionItem>()],
+    sell: [Trade; enum_iterator::cardinality::<SellItem>()],
+    buy: [Trade; enum_iterator::cardinality::<BuyItem>()],
+    upkeep: Upkeep,
+    skills: Skills,
+    deposits: Deposits,
+    storage: Storage,
+    buildings: [BuildingDef; enum_iterator::cardinality::<BuildingKind>()],
+    bank: Bank,
+    government: Government,
+}
+
+#[derive(Debug)]
+pub enum ContentError {
+    Io(io::Error),
+    UnsupportedFormat,
+    Parse(String),
+    InvalidEntry { entry: String, message: String },
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContentError::Io(e) => write!(f, "{}", e),
+            ContentError::UnsupportedFormat => write!(f, "expected a .toml or .json file"),
+            ContentError::Parse(e) => write!(f, "{}", e),
+            ContentError::InvalidEntry { entry, message } => write!(f, "{}: {}", entry, message),
+        }
+    }
+}
+
+impl From<io::Error> for ContentError {
+    fn from(e: io::Error) -> Self {
+        ContentError::Io(e)
+    }
+}
+
+fn invalid(entry: String, message: impl Into<String>) -> ContentError {
+    ContentError::InvalidEntry {
+        entry,
+        message: message.into(),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResourceEntry {
+    gatherable: Option<bool>,
+    capacity: Option<u32>,
+    decay: Option<u32>,
+}
+
+/// Every field is optional so that a content file can change a single one.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeEntry {
+    cost: Option<BTreeMap<String, u32>>,
+    output: Option<BTreeMap<String, u32>>,
+    workers: Option<u32>,
+    training: Option<BTreeMap<String, f64>>,
+    building: Option<String>,
+    time: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TradeEntry {
+    quantity: Option<u32>,
+    price: Option<u64>,
+}
+
+/// Every field is optional so that a content file can change a single one.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpkeepEntry {
+    period: Option<f64>,
+    wage: Option<u64>,
+    idle_wage: Option<u64>,
+    food: Option<BTreeMap<String, u32>>,
+    strike_after: Option<u32>,
+    desert_after: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkillsEntry {
+    levels: Option<Vec<f64>>,
+    bonus: Option<u32>,
+    settle_in: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DepositsEntry {
+    start: Option<u32>,
+    min_yield: Option<u32>,
+    explore_cost: Option<u64>,
+    explore_time: Option<f64>,
+    min_reserve: Option<u32>,
+    max_reserve: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StorageEntry {
+    overflow: Option<Overflow>,
+    overflow_price: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BankEntry {
+    loan_interest: Option<f64>,
+    deposit_interest: Option<f64>,
+    base_credit: Option<u64>,
+    credit_percent: Option<u32>,
+    max_periods: Option<u32>,
+    default_after: Option<u32>,
+    lockout: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GovernmentEntry {
+    sales_tax: Option<u32>,
+    transaction_fee: Option<u32>,
+    property_tax: Option<u32>,
+    spending: Option<u32>,
+    premium: Option<u32>,
+    works: Option<BTreeMap<String, u32>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BuildingEntry {
+    cost: Option<BTreeMap<String, u32>>,
+    time: Option<f64>,
+    slots: Option<u32>,
+    maintenance: Option<u64>,
+    storage: Option<u32>,
+    gather_bonus: Option<u32>,
+    start: Option<u32>,
+    buildable: Option<bool>,
+}
+
+/// The layout of a content file. Entries are keyed by name so that a misspelt one can be
+/// reported by name rather than as a parse error.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContentFile {
+    #[serde(default)]
+    resources: BTreeMap<String, ResourceEntry>,
+    #[serde(default)]
+    production: BTreeMap<String, RecipeEntry>,
+    #[serde(default)]
+    sell: BTreeMap<String, TradeEntry>,
+    #[serde(default)]
+    buy: BTreeMap<String, TradeEntry>,
+    upkeep: Option<UpkeepEntry>,
+    skills: Option<SkillsEntry>,
+    deposits: Option<DepositsEntry>,
+    storage: Option<StorageEntry>,
+    #[serde(default)]
+    buildings: BTreeMap<String, BuildingEntry>,
+    bank: Option<BankEntry>,
+    government: Option<GovernmentEntry>,
+}
+
+/// Finds the variant of `T` named `name`. Content cannot define entries of its own, so any other
+/// name is an error.
+fn lookup<T: enum_iterator::Sequence + fmt::Display>(
+    section: &str,
+    name: &str,
+) -> Result<T, ContentError> {
+    enum_iterator::all::<T>()
+        .find(|item| item.to_string() == name)
+        .ok_or_else(|| invalid(format!("{}.{}", section, name), "unknown entry"))
+}
+
+fn resource_amount(
+    entry: &str,
+    amounts: &BTreeMap<String, u32>,
+) -> Result<ResourceAmount, ContentError> {
+    let mut amount = ResourceAmount::new();
+    for (name, count) in amounts {
+        let resource = name
+            .parse::<Resource>()
+            .map_err(|e| invalid(entry.to_owned(), e))?;
+        *amount.get_mut(resource) = *count;
+    }
+    Ok(amount)
+}
+
+/// Converts a number of seconds from a content file into ticks, rejecting negative and non-finite
+/// values.
+fn seconds(entry: &str, seconds: f64) -> Result<Duration, ContentError> {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(invalid(
+            entry.to_owned(),
+            "must be a non-negative number of seconds",
+        ));
+    }
+    Ok(std::time::Duration::from_secs_f64(seconds).into())
+}
+
+/// Converts an interest rate in percent per minute from a content file into billionths per tick,
+/// rejecting negative and non-finite values.
+fn interest(entry: &str, percent: f64) -> Result<u64, ContentError> {
+    if !percent.is_finite() || percent < 0.0 {
+        return Err(invalid(
+            entry.to_owned(),
+            "must be a non-negative percentage per minute",
+        ));
+    }
+    let ticks_per_minute = (60 * Duration::TICKS_PER_SEC) as f64;
+    Ok((percent / 100.0 * RATE_SCALE as f64 / ticks_per_minute).round() as u64)
+}
+
+fn training(entry: &str, amounts: &BTreeMap<String, f64>) -> Result<ResourceAmount, ContentError> {
+    let mut amount = ResourceAmount::new();
+    for (name, secs) in amounts {
+        let resource = name
+            .parse::<Resource>()
+            .map_err(|e| invalid(entry.to_owned(), e))?;
+        *amount.get_mut(resource) = seconds(entry, *secs)?.ticks as u32;
+    }
+    Ok(amount)
+}
+
+fn merge_trade(resource: Resource, trade: &mut Trade, entry: &TradeEntry) {
+    if let Some(quantity) = entry.quantity {
+        *trade.give.get_mut(resource) = quantity;
+    }
+    trade.receive = entry.price.unwrap_or(trade.receive);
+}
+
+/// The market quotes whole units of money per unit of a resource, so a batch must be worth at
+/// least one per unit.
+fn validate_trade(entry: String, resource: Resource, trade: &Trade) -> Result<(), ContentError> {
+    let quantity = trade.give.get(resource);
+    if quantity == 0 {
+        return Err(invalid(entry, "quantity must be positive"));
+    }
+    if trade.receive < quantity as u64 {
+        return Err(invalid(entry, "price must be at least the quantity"));
+    }
+    Ok(())
+}
+
+impl Content {
+    /// The built-in ruleset overridden by the entries of the content file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ContentError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let file = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => {
+                toml::from_str(&text).map_err(|e| ContentError::Parse(e.to_string()))?
+            }
+            Some("json") => {
+                serde_json::from_str(&text).map_err(|e| ContentError::Parse(e.to_string()))?
+            }
+            _ => return Err(ContentError::UnsupportedFormat),
+        };
+        let mut content = Content::builtin();
+        content.apply(file)?;
+        content.validate()?;
+        Ok(content)
+    }
+
+    fn builtin() -> Self {
+        let file = toml::from_str(BUILTIN).expect("built-in content parses");
+        let mut content = Content::default();
+        content.apply(file).expect("built-in content is valid");
+        content.validate().expect("built-in content is valid");
+        content
+    }
+
+    fn apply(&mut self, file: ContentFile) -> Result<(), ContentError> {
+        for (name, entry) in file.resources {
+            let resource: Resource = lookup("resources", &name)?;
+            let def = &mut self.resources[resource as usize];
+            def.gatherable = entry.gatherable.unwrap_or(def.gatherable);
+            def.capacity = entry.capacity.unwrap_or(def.capacity);
+            def.decay = entry.decay.unwrap_or(def.decay);
+        }
+        for (name, entry) in file.production {
+            let item: ProductionItem = lookup("production", &name)?;
+            let entry_name = format!("production.{}", name);
+            let recipe = &mut self.production[item as usize];
+            if let Some(cost) = entry.cost {
+                recipe.cost = resource_amount(&entry_name, &cost)?;
+            }
+            if let Some(output) = entry.output {
+                recipe.output = resource_amount(&entry_name, &output)?;
+            }
+            recipe.workers = entry.workers.unwrap_or(recipe.workers);
+            if let Some(amounts) = entry.training {
+                recipe.training = training(&entry_name, &amounts)?;
+            }
+            if let Some(name) = entry.building {
+                recipe.building = name.parse().map_err(|e| invalid(entry_name.clone(), e))?;
+            }
+            if let Some(time) = entry.time {
+                if !time.is_finite() || time <= 0.0 {
+                    return Err(invalid(
+                        entry_name,
+                        "time must be a positive number of seconds",
+                    ));
+                }
+                recipe.time = std::time::Duration::from_secs_f64(time).into();
+            }
+        }
+        for (name, entry) in file.sell {
+            let item: SellItem = lookup("sell", &name)?;
+            merge_trade(item.get_resource(), &mut self.sell[item as usize], &entry);
+        }
+        for (name, entry) in file.buy {
+            let item: BuyItem = lookup("buy", &name)?;
+            merge_trade(item.get_resource(), &mut self.buy[item as usize], &entry);
+        }
+        if let Some(entry) = file.upkeep {
+            let upkeep = &mut self.upkeep;
+            if let Some(period) = entry.period {
+                if !period.is_finite() || period <= 0.0 {
+                    return Err(invalid(
+                        "upkeep.period".to_owned(),
+                        "must be a positive number of seconds",
+                    ));
+                }
+                upkeep.period = std::time::Duration::from_secs_f64(period).into();
+            }
+            upkeep.wage = entry.wage.unwrap_or(upkeep.wage);
+            upkeep.idle_wage = entry.idle_wage.unwrap_or(upkeep.idle_wage);
+            if let Some(food) = entry.food {
+                upkeep.food = resource_amount("upkeep.food", &food)?;
+            }
+            upkeep.strike_after = entry.strike_after.unwrap_or(upkeep.strike_after);
+            upkeep.desert_after = entry.desert_after.unwrap_or(upkeep.desert_after);
+        }
+        if let Some(entry) = file.skills {
+            let skills = &mut self.skills;
+            if let Some(levels) = entry.levels {
+                skills.levels = levels
+                    .into_iter()
+                    .map(|secs| seconds("skills.levels", secs).map(|d| d.ticks as u32))
+                    .collect::<Result<_, _>>()?;
+            }
+            skills.bonus = entry.bonus.unwrap_or(skills.bonus);
+            if let Some(settle_in) = entry.settle_in {
+                skills.settle_in = seconds("skills.settle_in", settle_in)?;
+            }
+        }
+        if let Some(entry) = file.deposits {
+            let deposits = &mut self.deposits;
+            deposits.start = entry.start.unwrap_or(deposits.start);
+            deposits.min_yield = entry.min_yield.unwrap_or(deposits.min_yield);
+            deposits.explore_cost = entry.explore_cost.unwrap_or(deposits.explore_cost);
+            if let Some(explore_time) = entry.explore_time {
+                deposits.explore_time = seconds("deposits.explore_time", explore_time)?;
+            }
+            deposits.min_reserve = entry.min_reserve.unwrap_or(deposits.min_reserve);
+            deposits.max_reserve = entry.max_reserve.unwrap_or(deposits.max_reserve);
+        }
+        if let Some(entry) = file.storage {
+            let storage = &mut self.storage;
+            storage.overflow = entry.overflow.unwrap_or(storage.overflow);
+            storage.overflow_price = entry.overflow_price.unwrap_or(storage.overflow_price);
+        }
+        for (name, entry) in file.buildings {
+            let kind: BuildingKind = lookup("buildings", &name)?;
+            let entry_name = format!("buildings.{}", name);
+            let def = &mut self.buildings[kind as usize];
+            if let Some(cost) = entry.cost {
+                def.cost = resource_amount(&entry_name, &cost)?;
+            }
+            if let Some(time) = entry.time {
+                def.time = seconds(&entry_name, time)?;
+            }
+            def.slots = entry.slots.unwrap_or(def.slots);
+            def.maintenance = entry.maintenance.unwrap_or(def.maintenance);
+            def.storage = entry.storage.unwrap_or(def.storage);
+            def.gather_bonus = entry.gather_bonus.unwrap_or(def.gather_bonus);
+            def.start = entry.start.unwrap_or(def.start);
+            def.buildable = entry.buildable.unwrap_or(def.buildable);
+        }
+        if let Some(entry) = file.bank {
+            let bank = &mut self.bank;
+            if let Some(rate) = entry.loan_interest {
+                bank.loan_interest = interest("bank.loan_interest", rate)?;
+            }
+            if let Some(rate) = entry.deposit_interest {
+                bank.deposit_interest = interest("bank.deposit_interest", rate)?;
+            }
+            bank.base_credit = entry.base_credit.unwrap_or(bank.base_credit);
+            bank.credit_percent = entry.credit_percent.unwrap_or(bank.credit_percent);
+            bank.max_periods = entry.max_periods.unwrap_or(bank.max_periods);
+            bank.default_after = entry.default_after.unwrap_or(bank.default_after);
+            if let Some(lockout) = entry.lockout {
+                bank.lockout = seconds("bank.lockout", lockout)?;
+            }
+        }
+        if let Some(entry) = file.government {
+            let government = &mut self.government;
+            government.sales_tax = entry.sales_tax.unwrap_or(government.sales_tax);
+            government.transaction_fee =
+                entry.transaction_fee.unwrap_or(government.transaction_fee);
+            government.property_tax = entry.property_tax.unwrap_or(government.property_tax);
+            government.spending = entry.spending.unwrap_or(government.spending);
+            government.premium = entry.premium.unwrap_or(government.premium);
+            if let Some(works) = entry.works {
+                government.works = resource_amount("government.works", &works)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ContentError> {
+        if !enum_iterator::all::<Resource>().any(|res| self.get_resource(res).gatherable) {
+            return Err(invalid(
+                "resources".to_owned(),
+                "at least one resource must be gatherable",
+            ));
+        }
+        for res in enum_iterator::all::<Resource>() {
+            let def = self.get_resource(res);
+            let entry = format!("resources.{}", res);
+            if def.capacity == 0 {
+                return Err(invalid(entry, "capacity must be positive"));
+            }
+            if def.decay > 100 {
+                return Err(invalid(entry, "decay must be a percentage"));
+            }
+        }
+        if self.storage.overflow_price > 100 {
+            return Err(invalid(
+                "storage.overflow_price".to_owned(),
+                "must be a percentage",
+            ));
+        }
+        for item in enum_iterator::all::<ProductionItem>() {
+            let recipe = self.get_recipe(item);
+            let entry = format!("production.{}", item);
+            if recipe.time.ticks == 0 {
+                return Err(invalid(entry, "time must be at least one tick"));
+            }
+            if recipe.workers == 0
+                && recipe.output.iter().all(|count| *count == 0)
+                && recipe.training.iter().all(|ticks| *ticks == 0)
+            {
+                return Err(invalid(entry, "produces nothing"));
+            }
+            let building = self.get_building(recipe.building);
+            if building.start == 0 && !building.buildable {
+                return Err(invalid(
+                    entry,
+                    format!("nobody can get a {} to make it in", recipe.building),
+                ));
+            }
+        }
+        for kind in enum_iterator::all::<BuildingKind>() {
+            let def = self.get_building(kind);
+            if def.buildable && def.time.ticks == 0 {
+                return Err(invalid(
+                    format!("buildings.{}", kind),
+                    "time must be at least one tick",
+                ));
+            }
+        }
+        if self.upkeep.period.ticks == 0 {
+            return Err(invalid(
+                "upkeep.period".to_owned(),
+                "must be at least one tick",
+            ));
+        }
+        if self.upkeep.strike_after == 0 || self.upkeep.desert_after == 0 {
+            return Err(invalid(
+                "upkeep".to_owned(),
+                "strike_after and desert_after must be positive",
+            ));
+        }
+        if !self.skills.levels.is_sorted_by(|a, b| a < b) {
+            return Err(invalid(
+                "skills.levels".to_owned(),
+                "levels must be in ascending order",
+            ));
+        }
+        let deposits = &self.deposits;
+        if deposits.start == 0 || deposits.min_reserve == 0 {
+            return Err(invalid(
+                "deposits".to_owned(),
+                "start and min_reserve must be positive",
+            ));
+        }
+        if deposits.min_reserve > deposits.max_reserve {
+            return Err(invalid(
+                "deposits".to_owned(),
+                "min_reserve must not exceed max_reserve",
+            ));
+        }
+        if deposits.min_yield > 100 {
+            return Err(invalid(
+                "deposits.min_yield".to_owned(),
+                "must be a percentage",
+            ));
+        }
+        if deposits.explore_time.ticks == 0 {
+            return Err(invalid(
+                "deposits.explore_time".to_owned(),
+                "must be at least one tick",
+            ));
+        }
+        if self.bank.max_periods == 0 || self.bank.default_after == 0 {
+            return Err(invalid(
+                "bank".to_owned(),
+                "max_periods and default_after must be positive",
+            ));
+        }
+        let government = &self.government;
+        if government.sales_tax > 100
+            || government.transaction_fee > 100
+            || government.property_tax > 100
+            || government.spending > 100
+        {
+            return Err(invalid(
+                "government".to_owned(),
+                "taxes, fees and spending are percentages of at most 100",
+            ));
+        }
+        for item in enum_iterator::all::<SellItem>() {
+            validate_trade(
+                format!("sell.{}", item),
+                item.get_resource(),
+                &self.get_sell_trade(item),
+            )?;
+        }
+        for item in enum_iterator::all::<BuyItem>() {
+            validate_trade(
+                format!("buy.{}", item),
+                item.get_resource(),
+                &self.get_buy_trade(item),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn get_resource(&self, resource: Resource) -> &ResourceDef {
+        &self.resources[resource as usize]
+    }
+
+    pub fn get_recipe(&self, item: ProductionItem) -> &Recipe {
+        &self.production[item as usize]
+    }
+
+    pub fn get_sell_trade(&self, item: SellItem) -> Trade {
+        self.sell[item as usize]
+    }
+
+    pub fn get_buy_trade(&self, item: BuyItem) -> Trade {
+        self.buy[item as usize]
+    }
+
+    pub fn get_upkeep(&self) -> &Upkeep {
+        &self.upkeep
+    }
+
+    pub fn get_skills(&self) -> &Skills {
+        &self.skills
+    }
+
+    pub fn get_deposits(&self) -> &Deposits {
+        &self.deposits
+    }
+
+    pub fn get_storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    pub fn get_building(&self, kind: BuildingKind) -> &BuildingDef {
+        &self.buildings[kind as usize]
+    }
+
+    pub fn get_bank(&self) -> &Bank {
+        &self.bank
+    }
+
+    pub fn get_government(&self) -> &Government {
+        &self.government
+    }
+
+    /// A hash of every value of the ruleset, which tells whether two games follow the same rules.
+    /// It is an FNV-1a hash of the debug representation, so that it stays the same across builds.
+    pub fn fingerprint(&self) -> u64 {
+        format!("{:?}", self)
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Installs the content used for the rest of the run. Returns false if content is already in use.
+pub fn set(content: Content) -> bool {
+    CONTENT.set(content).is_ok()
+}
+
+/// The content in use, which is the built-in ruleset unless `set` was called first.
+pub fn get() -> &'static Content {
+    CONTENT.get_or_init(Content::builtin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overridden(text: &str) -> Result<Content, ContentError> {
+        let mut content = Content::builtin();
+        content.apply(toml::from_str(text).unwrap())?;
+        content.validate()?;
+        Ok(content)
+    }
+
+    #[test]
+    fn entries_change_only_the_fields_given() {
+        let builtin = Content::builtin();
+        let content =
+            overridden("[production.Tool]\ntime = 60\n\n[sell.Brick]\nprice = 900\n").unwrap();
+        let tool = content.get_recipe(ProductionItem::Tool);
+        let builtin_tool = builtin.get_recipe(ProductionItem::Tool);
+        assert_eq!(tool.time.ticks, 60 * Duration::TICKS_PER_SEC);
+        assert_eq!(tool.cost, builtin_tool.cost);
+        assert_eq!(tool.output, builtin_tool.output);
+        assert_eq!(tool.training, builtin_tool.training);
+        assert_eq!(tool.building, builtin_tool.building);
+        let brick = content.get_sell_trade(SellItem::Brick);
+        assert_eq!(brick.receive, 900);
+        assert_eq!(brick.give, builtin.get_sell_trade(SellItem::Brick).give);
+    }
+
+    #[test]
+    fn fingerprints_tell_rulesets_apart() {
+        let builtin = Content::builtin().fingerprint();
+        assert_eq!(builtin, Content::builtin().fingerprint());
+        assert_eq!(builtin, overridden("").unwrap().fingerprint());
+        let changed = overridden("[bank]\nbase_credit = 1\n").unwrap();
+        assert_ne!(builtin, changed.fingerprint());
+    }
+
+    #[test]
+    fn invalid_entries_are_named() {
+        let error = overridden("[production.Tool]\ncost = { Irn = 3 }\n").unwrap_err();
+        assert_eq!(error.to_string(), "production.Tool: unknown resource Irn");
+        let error = overridden("[production.Tool]\ntime = 0\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "production.Tool: time must be a positive number of seconds"
+        );
+    }
+}
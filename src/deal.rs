@@ -0,0 +1,218 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_state::ActionError;
+use crate::market::Side;
+use crate::player::{Player, PlayerId};
+use crate::resource::{Resource, ResourceAmount};
+
+/// Identifies a trade offer, and the contract it becomes once accepted.
+pub type DealId = u64;
+
+/// Resources and money changing hands in a deal.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Bundle {
+    pub resources: ResourceAmount,
+    pub money: u64,
+}
+
+impl Bundle {
+    pub fn is_empty(&self) -> bool {
+        self.money == 0 && self.resources.iter().all(|amount| *amount == 0)
+    }
+
+    /// Takes the bundle from `player`, or nothing at all if they do not have all of it.
+    pub fn take_from(&self, player: &mut Player) -> Result<(), ActionError> {
+        if player.get_money() < self.money {
+            return Err(ActionError::InsufficientMoney);
+        }
+        if !player.get_stockpile_mut().consume(&self.resources) {
+            return Err(ActionError::InsufficientResources);
+        }
+        player.spend_money(self.money);
+        Ok(())
+    }
+
+    pub fn give_to(&self, player: &mut Player) {
+        player.get_stockpile_mut().add(&self.resources);
+        player.add_money(self.money);
+    }
+}
+
+impl fmt::Display for Bundle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<_> = enum_iterator::all::<Resource>()
+            .filter(|res| self.resources.get(*res) > 0)
+            .map(|res| format!("{} {}", self.resources.get(res), res))
+            .collect();
+        if self.money > 0 {
+            parts.push(format!("{} money", self.money));
+        }
+        if parts.is_empty() {
+            write!(f, "nothing")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// The supplier of a contract hands `quantity` of `resource` to the buyer every tick, for `ticks`
+/// ticks, and is paid `price` for every delivery. Whoever cannot keep their side of a delivery
+/// pays the other `penalty` instead, or as much of it as they have.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ContractTerms {
+    pub resource: Resource,
+    pub quantity: u32,
+    pub price: u64,
+    pub ticks: u64,
+    pub penalty: u64,
+}
+
+impl fmt::Display for ContractTerms {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} a tick for {} money, {} ticks, penalty {}",
+            self.quantity, self.resource, self.price, self.ticks, self.penalty
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Deal {
+    /// The proposer hands over `give` in exchange for `receive`.
+    Exchange { give: Bundle, receive: Bundle },
+    /// A contract in which the proposer is the supplier (`Side::Sell`) or the buyer
+    /// (`Side::Buy`).
+    Contract(Side, ContractTerms),
+}
+
+impl Deal {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Deal::Exchange { give, receive } => !give.is_empty() || !receive.is_empty(),
+            Deal::Contract(_, terms) => terms.quantity > 0 && terms.ticks > 0,
+        }
+    }
+
+    /// What the proposer pays up front, which is held until the offer is accepted or declined.
+    pub fn get_escrow(&self) -> Bundle {
+        match self {
+            Deal::Exchange { give, .. } => *give,
+            Deal::Contract(_, _) => Bundle::default(),
+        }
+    }
+}
+
+impl fmt::Display for Deal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Deal::Exchange { give, receive } => write!(f, "gives {} for {}", give, receive),
+            Deal::Contract(Side::Sell, terms) => write!(f, "supplies {}", terms),
+            Deal::Contract(Side::Buy, terms) => write!(f, "buys {}", terms),
+        }
+    }
+}
+
+/// A deal one player proposes to another, waiting to be accepted, countered or declined.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TradeOffer {
+    pub id: DealId,
+    pub from: PlayerId,
+    pub to: PlayerId,
+    pub deal: Deal,
+}
+
+/// An accepted contract being delivered on.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Contract {
+    pub id: DealId,
+    pub supplier: PlayerId,
+    pub buyer: PlayerId,
+    pub terms: ContractTerms,
+    /// Deliveries still to be made.
+    pub remaining: u64,
+    /// Deliveries that were missed, by either side.
+    pub defaults: u32,
+}
+
+impl fmt::Display for Contract {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Player {} supplies Player {} {} {} a tick for {} money, penalty {}",
+            self.supplier,
+            self.buyer,
+            self.terms.quantity,
+            self.terms.resource,
+            self.terms.price,
+            self.terms.penalty
+        )
+    }
+}
+
+/// The open trade offers and running contracts between players.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DealBook {
+    next_id: DealId,
+    offers: Vec<TradeOffer>,
+    contracts: Vec<Contract>,
+}
+
+impl DealBook {
+    pub fn propose(&mut self, from: PlayerId, to: PlayerId, deal: Deal) -> DealId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.offers.push(TradeOffer { id, from, to, deal });
+        id
+    }
+
+    pub fn get_offer(&self, id: DealId) -> Option<&TradeOffer> {
+        self.offers.iter().find(|offer| offer.id == id)
+    }
+
+    pub fn take_offer(&mut self, id: DealId) -> Option<TradeOffer> {
+        let index = self.offers.iter().position(|offer| offer.id == id)?;
+        Some(self.offers.remove(index))
+    }
+
+    /// The offers `player` made or received, oldest first.
+    pub fn offers_of(&self, player: PlayerId) -> impl Iterator<Item = &TradeOffer> {
+        self.offers
+            .iter()
+            .filter(move |offer| offer.from == player || offer.to == player)
+    }
+
+    /// Turns an accepted contract offer into a running contract.
+    pub fn start_contract(&mut self, offer: &TradeOffer, side: Side, terms: ContractTerms) {
+        let (supplier, buyer) = match side {
+            Side::Sell => (offer.from, offer.to),
+            Side::Buy => (offer.to, offer.from),
+        };
+        self.contracts.push(Contract {
+            id: offer.id,
+            supplier,
+            buyer,
+            terms,
+            remaining: terms.ticks,
+            defaults: 0,
+        });
+    }
+
+    /// The contracts `player` supplies or buys under, oldest first.
+    pub fn contracts_of(&self, player: PlayerId) -> impl Iterator<Item = &Contract> {
+        self.contracts
+            .iter()
+            .filter(move |c| c.supplier == player || c.buyer == player)
+    }
+
+    pub fn contracts_mut(&mut self) -> impl Iterator<Item = &mut Contract> {
+        self.contracts.iter_mut()
+    }
+
+    /// Drops the contracts with no deliveries left.
+    pub fn remove_finished(&mut self) {
+        self.contracts.retain(|c| c.remaining > 0);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::content;
+use crate::resource::Resource;
+
+/// A finite source of a gatherable resource. Its yield falls as it is worked, down to the
+/// content's `min_yield` just before it runs out.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Deposit {
+    resource: Resource,
+    initial: u32,
+    reserve: u32,
+}
+
+impl Deposit {
+    pub fn new(resource: Resource, reserve: u32) -> Self {
+        Deposit {
+            resource,
+            initial: reserve,
+            reserve,
+        }
+    }
+
+    pub fn get_resource(&self) -> Resource {
+        self.resource
+    }
+
+    pub fn get_initial(&self) -> u32 {
+        self.initial
+    }
+
+    pub fn get_reserve(&self) -> u32 {
+        self.reserve
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.reserve == 0
+    }
+
+    /// The percentage of a full yield gathered from the deposit.
+    pub fn get_yield(&self) -> u32 {
+        if self.is_exhausted() {
+            return 0;
+        }
+        let min_yield = content::get().get_deposits().min_yield as u64;
+        let remaining = self.reserve as u64 * 100 / self.initial as u64;
+        (min_yield + (100 - min_yield) * remaining / 100) as u32
+    }
+
+    /// Takes up to `units` from the deposit and returns how many there were.
+    pub fn take(&mut self, units: u32) -> u32 {
+        let taken = units.min(self.reserve);
+        self.reserve -= taken;
+        taken
+    }
+}
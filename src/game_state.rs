@@ -1,26 +1,223 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use ratatui::{
     layout::Constraint,
     style::{Color, Modifier, Style},
     text::Span,
     widgets::{Block, BorderType, Borders, Cell, Row, Table},
 };
-use std::fmt;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt};
 
-use crate::player::{Player, PlayerId, WorkerAction};
+use crate::bank::LoanId;
+use crate::building::{Building, BuildingId, BuildingKind, ProductionId};
+use crate::buy::BuyItem;
+use crate::content::{self, Overflow};
+use crate::deal::{Deal, DealBook, DealId};
+use crate::deposit::Deposit;
+use crate::government::{Government, Levy};
+use crate::journal::{Journal, JournalEvent};
+use crate::market::{Fill, Market, OrderId, OrderRequest, Participant, Side};
+use crate::npc::{ConsumerSector, NpcItem, ProducerSector};
+use crate::player::{Player, PlayerId, WorkerAction, MAX_PLAYERS};
+use crate::policy::{ActionPolicy, ActionSource};
 use crate::production::ProductionItem;
 use crate::resource::Resource;
-use crate::sell::{ConsumerSector, SellItem, Trade};
+use crate::rules::Rule;
+use crate::sell::{SellItem, Trade};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub enum GameAction {
     AllocateWorker(PlayerId, Resource),
     DeallocateWorker(PlayerId, Resource),
     TogglePause,
     Produce(PlayerId, ProductionItem),
     Sell(PlayerId, SellItem),
+    Buy(PlayerId, BuyItem),
+    PlaceOrder(PlayerId, OrderRequest),
+    CancelOrder(PlayerId, OrderId),
+    Explore(PlayerId, Resource),
+    Build(PlayerId, BuildingKind),
+    StaffBuilding(PlayerId, BuildingKind),
+    UnstaffBuilding(PlayerId, BuildingKind),
+    CancelProduction(PlayerId, ProductionId),
+    MoveProduction(PlayerId, ProductionId, usize),
+    RepeatProduction(PlayerId, ProductionId, bool),
+    AddRule(PlayerId, Rule),
+    RemoveRule(PlayerId, usize),
+    ToggleRule(PlayerId, usize),
+    /// Offers a deal to another player.
+    ProposeDeal(PlayerId, PlayerId, Deal),
+    AcceptDeal(PlayerId, DealId),
+    /// Declines an offer received, or withdraws one made.
+    DeclineDeal(PlayerId, DealId),
+    /// Declines an offer received and proposes another deal to its sender instead.
+    CounterDeal(PlayerId, DealId, Deal),
+    /// Borrows money from the bank, to be repaid over a number of upkeep periods.
+    TakeLoan(PlayerId, u64, u32),
+    RepayLoan(PlayerId, LoanId, u64),
+    DepositMoney(PlayerId, u64),
+    WithdrawMoney(PlayerId, u64),
 }
 
-#[derive(Clone, Copy, Debug)]
+impl GameAction {
+    pub fn get_player(&self) -> Option<PlayerId> {
+        match self {
+            GameAction::AllocateWorker(player, _)
+            | GameAction::DeallocateWorker(player, _)
+            | GameAction::Produce(player, _)
+            | GameAction::Sell(player, _)
+            | GameAction::Buy(player, _)
+            | GameAction::PlaceOrder(player, _)
+            | GameAction::CancelOrder(player, _)
+            | GameAction::Explore(player, _)
+            | GameAction::Build(player, _)
+            | GameAction::StaffBuilding(player, _)
+            | GameAction::UnstaffBuilding(player, _)
+            | GameAction::CancelProduction(player, _)
+            | GameAction::MoveProduction(player, _, _)
+            | GameAction::RepeatProduction(player, _, _)
+            | GameAction::AddRule(player, _)
+            | GameAction::RemoveRule(player, _)
+            | GameAction::ToggleRule(player, _)
+            | GameAction::ProposeDeal(player, _, _)
+            | GameAction::AcceptDeal(player, _)
+            | GameAction::DeclineDeal(player, _)
+            | GameAction::CounterDeal(player, _, _)
+            | GameAction::TakeLoan(player, _, _)
+            | GameAction::RepayLoan(player, _, _)
+            | GameAction::DepositMoney(player, _)
+            | GameAction::WithdrawMoney(player, _) => Some(*player),
+            GameAction::TogglePause => None,
+        }
+    }
+
+    /// The same action taken on behalf of `id`. Actions that belong to no player are unchanged.
+    pub fn with_player(mut self, id: PlayerId) -> Self {
+        match &mut self {
+            GameAction::AllocateWorker(player, _)
+            | GameAction::DeallocateWorker(player, _)
+            | GameAction::Produce(player, _)
+            | GameAction::Sell(player, _)
+            | GameAction::Buy(player, _)
+            | GameAction::PlaceOrder(player, _)
+            | GameAction::CancelOrder(player, _)
+            | GameAction::Explore(player, _)
+            | GameAction::Build(player, _)
+            | GameAction::StaffBuilding(player, _)
+            | GameAction::UnstaffBuilding(player, _)
+            | GameAction::CancelProduction(player, _)
+            | GameAction::MoveProduction(player, _, _)
+            | GameAction::RepeatProduction(player, _, _)
+            | GameAction::AddRule(player, _)
+            | GameAction::RemoveRule(player, _)
+            | GameAction::ToggleRule(player, _)
+            | GameAction::ProposeDeal(player, _, _)
+            | GameAction::AcceptDeal(player, _)
+            | GameAction::DeclineDeal(player, _)
+            | GameAction::CounterDeal(player, _, _)
+            | GameAction::TakeLoan(player, _, _)
+            | GameAction::RepayLoan(player, _, _)
+            | GameAction::DepositMoney(player, _)
+            | GameAction::WithdrawMoney(player, _) => *player = id,
+            GameAction::TogglePause => (),
+        }
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ActionError {
+    UnknownPlayer,
+    NotPermitted,
+    NoIdleWorker,
+    NoAllocatedWorker,
+    NotGatherable,
+    InsufficientResources,
+    InsufficientMoney,
+    InvalidOrder,
+    UnknownOrder,
+    NoBuyers,
+    NoSellers,
+    RateLimited,
+    CannotAffordFee,
+    NoBuilding,
+    NotBuildable,
+    NoFreeSlot,
+    NoStaff,
+    UnknownProduction,
+    UnknownRule,
+    UnknownDeal,
+    InvalidDeal,
+    CreditLimit,
+    UnknownLoan,
+    InvalidAmount,
+    InvalidTerm,
+    NoWorker,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ActionOutcome {
+    Applied,
+    Delayed { until_tick: u64 },
+}
+
+/// What became of a delayed action once its tick came.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct DelayedResult {
+    pub tick: u64,
+    pub action: GameAction,
+    pub result: Result<(), ActionError>,
+}
+
+/// An action from the API waiting for its tick.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct DelayedAction {
+    until_tick: u64,
+    action: GameAction,
+    /// The rule of the action's player that took it, if one did.
+    rule: Option<usize>,
+}
+
+/// How many results of delayed actions are kept for a player who does not collect them.
+const MAX_DELAYED_RESULTS: usize = 100;
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::UnknownPlayer => write!(f, "unknown player"),
+            ActionError::NotPermitted => write!(f, "action not permitted"),
+            ActionError::NoIdleWorker => write!(f, "no idle worker available"),
+            ActionError::NoAllocatedWorker => write!(f, "no worker allocated to that resource"),
+            ActionError::NotGatherable => write!(f, "that resource can only be produced"),
+            ActionError::InsufficientResources => write!(f, "insufficient resources"),
+            ActionError::InsufficientMoney => write!(f, "insufficient money"),
+            ActionError::InvalidOrder => write!(f, "order quantity must be positive"),
+            ActionError::UnknownOrder => write!(f, "no such order"),
+            ActionError::NoBuyers => write!(f, "no buyers in the market"),
+            ActionError::NoSellers => write!(f, "no sellers in the market"),
+            ActionError::RateLimited => write!(f, "too many actions this tick"),
+            ActionError::CannotAffordFee => write!(f, "cannot afford the API fee"),
+            ActionError::NoBuilding => write!(f, "no finished building of that kind"),
+            ActionError::NotBuildable => write!(f, "that building cannot be constructed"),
+            ActionError::NoFreeSlot => write!(f, "no free slot in a building of that kind"),
+            ActionError::NoStaff => write!(f, "nobody staffs a building of that kind"),
+            ActionError::UnknownProduction => write!(f, "no such item queued"),
+            ActionError::UnknownRule => write!(f, "no such rule"),
+            ActionError::UnknownDeal => write!(f, "no such trade offer"),
+            ActionError::InvalidDeal => {
+                write!(f, "a deal needs another player and something to trade")
+            }
+            ActionError::CreditLimit => write!(f, "the bank will not lend that much"),
+            ActionError::UnknownLoan => write!(f, "no such loan"),
+            ActionError::InvalidAmount => write!(f, "amount must be positive"),
+            ActionError::InvalidTerm => write!(f, "the bank does not lend over that many periods"),
+            ActionError::NoWorker => write!(f, "no worker to train"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct Duration {
     pub ticks: u64,
 }
@@ -37,31 +234,219 @@ impl From<std::time::Duration> for Duration {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct GameState {
     players: Vec<Player>,
     paused: bool,
+    tick: u64,
     consumer_sector: ConsumerSector,
+    producer_sector: ProducerSector,
+    market: Market,
+    deals: DealBook,
+    government: Government,
+    policy: ActionPolicy,
+    api_actions_this_tick: Vec<u32>,
+    delayed_actions: Vec<DelayedAction>,
+    delayed_results: Vec<Vec<DelayedResult>>,
+    seed: u64,
+    rng: GameRng,
+    #[serde(skip)]
+    journal: Option<Journal>,
+    /// Players whose client disconnected, free for the next client to take over.
+    #[serde(skip)]
+    vacant: BTreeSet<PlayerId>,
 }
 
+/// All randomness in the simulation is drawn from the state's own generator, so that a seed and
+/// a sequence of actions always lead to the same state.
+pub type GameRng = ChaCha8Rng;
+
 const TABLE_COLS: usize = enum_iterator::cardinality::<Resource>() + 2;
 const TABLE_WIDTHS: &[Constraint] = &[Constraint::Ratio(1, TABLE_COLS as u32); TABLE_COLS];
 
 impl GameState {
-    pub fn new() -> Self {
-        GameState {
+    pub fn new(seed: u64) -> Self {
+        let mut state = GameState {
             players: Vec::new(),
             paused: false,
+            tick: 0,
             consumer_sector: ConsumerSector::default(),
-        }
+            producer_sector: ProducerSector::default(),
+            market: Market::default(),
+            deals: DealBook::default(),
+            government: Government::default(),
+            policy: ActionPolicy::default(),
+            api_actions_this_tick: Vec::new(),
+            delayed_actions: Vec::new(),
+            delayed_results: Vec::new(),
+            seed,
+            rng: GameRng::seed_from_u64(seed),
+            journal: None,
+            vacant: BTreeSet::new(),
+        };
+        state.refresh_npc_orders();
+        state
     }
 
     pub fn step(&mut self) {
+        let tick = self.tick;
+        let (due, pending) = self
+            .delayed_actions
+            .drain(..)
+            .partition(|delayed: &DelayedAction| delayed.until_tick <= tick);
+        self.delayed_actions = pending;
+        for DelayedAction { action, rule, .. } in due {
+            let result = self.apply_action(action);
+            // Only actions of players are ever delayed.
+            let Some(player) = action.get_player() else {
+                continue;
+            };
+            if let Some(index) = rule {
+                self.players[player as usize].set_rule_error(index, result.err());
+            } else {
+                let results = &mut self.delayed_results[player as usize];
+                if results.len() == MAX_DELAYED_RESULTS {
+                    results.remove(0);
+                }
+                results.push(DelayedResult {
+                    tick,
+                    action,
+                    result,
+                });
+            }
+        }
+        self.fire_rules();
+        self.deliver_contracts();
+
+        let payday = tick > 0 && tick.is_multiple_of(self.get_pay_period());
+        let deposits = content::get().get_deposits();
+        let spoil = tick > 0 && tick.is_multiple_of(60 * Duration::TICKS_PER_SEC);
+        let storage = content::get().get_storage();
+        if payday {
+            for player in 0..self.players.len() {
+                let p = &mut self.players[player];
+                p.pay_wages();
+                // Property tax is levied on what is left after wages, as far as money allows.
+                let tax = p.get_property_tax().min(p.get_money());
+                p.spend_money(tax);
+                self.government.collect(Levy::PropertyTax, tax);
+                if p.pay_installments() {
+                    self.release_escrow(player as PlayerId);
+                    self.players[player].go_bankrupt(tick);
+                }
+            }
+        }
         for p in self.players.iter_mut() {
-            p.step();
+            p.step(tick);
+            for resource in p.advance_explorations() {
+                let reserve = self
+                    .rng
+                    .gen_range(deposits.min_reserve..=deposits.max_reserve);
+                p.add_deposit(Deposit::new(resource, reserve));
+            }
+            if spoil {
+                p.spoil();
+            }
+            let overflow = p.take_overflow();
+            if storage.overflow == Overflow::Sell {
+                // The consumer sector buys the overflow like any other sale, moving its price.
+                let mut value = 0;
+                for item in enum_iterator::all::<SellItem>() {
+                    let quantity = overflow.get(item.get_resource());
+                    if quantity == 0 {
+                        continue;
+                    }
+                    value += quantity as u64 * self.consumer_sector.get_unit_price(item);
+                    self.consumer_sector
+                        .record_trade(item.get_resource(), quantity, &mut self.rng);
+                }
+                let proceeds = value * storage.overflow_price as u64 / 100;
+                p.add_money(self.government.levy(Levy::SalesTax, proceeds));
+            }
+        }
+        self.refresh_npc_orders();
+        self.api_actions_this_tick.fill(0);
+        self.tick += 1;
+        if let Some(journal) = &mut self.journal {
+            journal.set_tick(self.tick);
+        }
+    }
+
+    /// Submits the action of every rule whose condition holds, as if it came from the API. Rules
+    /// are evaluated in order, each against the state the rules before it left behind, and a rule
+    /// whose last action is still delayed waits for it. Their actions are not journaled, as a
+    /// replay fires the same rules again.
+    fn fire_rules(&mut self) {
+        for player in 0..self.players.len() {
+            for index in 0..self.players[player].get_rules().len() {
+                if self.is_rule_pending(player as PlayerId, index) {
+                    continue;
+                }
+                let rule = self.players[player].get_rules()[index];
+                if let Some(action) = rule.fire(&self.players[player]) {
+                    match self.dispatch_action(action, ActionSource::Api, Some(index)) {
+                        Ok(ActionOutcome::Delayed { .. }) => (),
+                        result => self.players[player].set_rule_error(index, result.err()),
+                    }
+                }
+            }
         }
     }
 
+    /// Whether an action that rule `index` of the player took is still delayed.
+    pub fn is_rule_pending(&self, player: PlayerId, index: usize) -> bool {
+        self.delayed_actions.iter().any(|delayed| {
+            delayed.rule == Some(index) && delayed.action.get_player() == Some(player)
+        })
+    }
+
+    /// Records everything that changes the game from now on to `journal`.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
+    /// Stops recording, writing the end of the session to the journal. Other threads may still
+    /// hold the state when the session shuts down, so the journal cannot wait to be dropped.
+    pub fn end_journal(&mut self) {
+        self.journal = None;
+    }
+
+    fn record(&mut self, event: JournalEvent) {
+        if let Some(journal) = &mut self.journal {
+            journal.record(self.tick, event);
+        }
+    }
+
+    fn get_pay_period(&self) -> u64 {
+        content::get().get_upkeep().period.ticks
+    }
+
+    /// The tick at which workers are next paid.
+    pub fn get_next_payday(&self) -> u64 {
+        let period = self.get_pay_period();
+        (self.tick / period + 1) * period
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_policy(&self) -> &ActionPolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: ActionPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn players(&self) -> std::slice::Iter<'_, Player> {
+        self.players.iter()
+    }
+
     pub fn get_player(&self, player: PlayerId) -> &Player {
         &self.players[player as usize]
     }
@@ -70,7 +455,7 @@ impl GameState {
         &mut self.players[player as usize]
     }
 
-    pub fn resources_as_table(&self) -> Table {
+    pub fn resources_as_table(&self) -> Table<'_> {
         let header: Vec<_> = std::iter::once(Cell::from("Player Id"))
             .chain(std::iter::once(Cell::from("Money")))
             .chain(Resource::names().map(Cell::from))
@@ -79,8 +464,9 @@ impl GameState {
             let mut row = Vec::with_capacity(p.get_stockpile().iter().count() + 2);
             row.push(p.get_id().to_string());
             row.push(p.get_money().to_string());
-            for r in p.get_stockpile().iter() {
-                row.push(r.to_string());
+            for res in enum_iterator::all::<Resource>() {
+                let stock = p.get_stockpile().get(res);
+                row.push(format!("{}/{}", stock, p.get_capacity(res)));
             }
             row
         });
@@ -97,7 +483,133 @@ impl GameState {
             )
     }
 
-    pub fn player_workers_as_table(&self, player: PlayerId) -> Table {
+    pub fn player_deposits_as_table(&self, player: PlayerId) -> Table<'_> {
+        let p = &self.players[player as usize];
+        let header = Row::new(vec!["Deposit", "Reserve", "Yield"]);
+        let deposits = p.get_deposits().map(|d| {
+            Row::new(vec![
+                d.get_resource().to_string(),
+                format!("{} / {}", d.get_reserve(), d.get_initial()),
+                format!("{}%", d.get_yield()),
+            ])
+        });
+        let explorations = p.get_explorations().map(|(resource, remaining)| {
+            Row::new(vec![
+                resource.to_string(),
+                "Exploring".to_owned(),
+                format!("{}s", remaining.ticks.div_ceil(Duration::TICKS_PER_SEC)),
+            ])
+        });
+        Table::new(
+            deposits.chain(explorations),
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(50),
+                Constraint::Percentage(20),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title(format!(
+                    "Deposits (e: explore the selected resource for {})",
+                    content::get().get_deposits().explore_cost
+                ))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray)),
+        )
+    }
+
+    pub fn player_buildings_as_table(&self, player: PlayerId) -> Table<'_> {
+        let p = &self.players[player as usize];
+        let header = Row::new(vec!["Building", "Status", "Staff", "Queue"]);
+        let rows = p.get_buildings().zip(0..).map(|(b, id)| {
+            let status = if !b.is_finished() {
+                format!(
+                    "Building, {}s",
+                    b.get_construction().ticks.div_ceil(Duration::TICKS_PER_SEC)
+                )
+            } else if b.is_unpaid() {
+                "Unpaid".to_owned()
+            } else if b.get_queue().next().is_some() {
+                "Working".to_owned()
+            } else {
+                "Idle".to_owned()
+            };
+            let queue = b
+                .get_queue()
+                .map(|queued| queued.item.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Row::new(vec![
+                format!("{} {}", b.get_kind(), id),
+                status,
+                format!("{} / {}", p.get_staff(id), b.get_kind().get_slots()),
+                queue,
+            ])
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Percentage(20),
+                Constraint::Percentage(15),
+                Constraint::Percentage(10),
+                Constraint::Percentage(55),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title("Buildings")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray)),
+        )
+    }
+
+    /// Everything the player has queued, building by building.
+    pub fn player_queue_as_table(&self, player: PlayerId) -> Table<'_> {
+        let p = &self.players[player as usize];
+        let header = Row::new(vec!["Building", "Item", "Remaining", "Repeat"]);
+        let rows = p.get_production_queue().map(|(building, queued)| {
+            let kind = p.get_buildings().nth(building as usize).unwrap().get_kind();
+            let remaining = queued.remaining.ticks.div_ceil(Duration::TICKS_PER_SEC);
+            Row::new(vec![
+                format!("{} {}", kind, building),
+                queued.item.to_string(),
+                format!("{}s", remaining),
+                if queued.repeat { "Yes" } else { "No" }.to_owned(),
+            ])
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(30),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title("Queue")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>")
+    }
+
+    pub fn player_workers_as_table(&self, player: PlayerId) -> Table<'_> {
         let p = &self.players[player as usize];
         let idle_count = p
             .workers
@@ -108,23 +620,62 @@ impl GameState {
             Cell::from("Idle"),
             Cell::from(format!("  {}  ", idle_count)),
         ]));
-        let active_workers = enum_iterator::all::<Resource>().map(|res| {
-            let count = p
-                .workers
-                .iter()
-                .filter(|w| w.current_action == WorkerAction::Gather(res))
-                .count();
+        let level_count = content::get().get_skills().levels.len() + 1;
+        let active_workers = Resource::gatherable().map(|res| {
+            let gathering = || {
+                p.workers
+                    .iter()
+                    .filter(move |w| w.current_action == WorkerAction::Gather(res))
+            };
+            let count = gathering().count();
+            let mut levels = vec![0; level_count];
+            for w in gathering() {
+                levels[w.get_level(res)] += 1;
+            }
             let dec_symb = if count > 0 { "<" } else { " " };
             let inc_symb = if idle_count > 0 { ">" } else { " " };
             Row::new(vec![
                 Cell::from(res.to_string()),
                 Cell::from(format!("{} {} {}", dec_symb, count, inc_symb)),
+                Cell::from(
+                    levels
+                        .iter()
+                        .map(|n| n.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
             ])
         });
+        let (wages, _) = p.get_wage_bill();
+        let payday_in = (self.get_next_payday() - self.tick).div_ceil(Duration::TICKS_PER_SEC);
+        let staffing = p
+            .workers
+            .iter()
+            .filter(|w| matches!(w.current_action, WorkerAction::Staff(_)))
+            .count();
+        let upkeep_rows = [
+            ("Staffing".to_owned(), staffing.to_string()),
+            ("Unpaid".to_owned(), p.get_unpaid_workers().to_string()),
+            (format!("Upkeep in {}s", payday_in), wages.to_string()),
+            ("Property tax".to_owned(), p.get_property_tax().to_string()),
+        ]
+        .map(|(label, value)| {
+            Row::new(vec![Cell::from(label), Cell::from(format!("  {}", value))])
+        });
         Table::new(
-            idle_row.chain(active_workers),
-            [Constraint::Percentage(80), Constraint::Percentage(20)].iter(),
+            idle_row.chain(active_workers).chain(upkeep_rows),
+            [
+                Constraint::Percentage(45),
+                Constraint::Percentage(20),
+                Constraint::Percentage(35),
+            ]
+            .iter(),
         )
+        .header(Row::new(vec![
+            Cell::from(""),
+            Cell::from(""),
+            Cell::from(format!("Level 0-{}", level_count - 1)),
+        ]))
         .style(Style::default())
         .block(
             Block::default()
@@ -142,32 +693,36 @@ impl GameState {
         .highlight_symbol(">>")
     }
 
-    fn deallocate_player_worker(&mut self, player: PlayerId, r: Resource) -> bool {
+    fn deallocate_player_worker(
+        &mut self,
+        player: PlayerId,
+        r: Resource,
+    ) -> Result<(), ActionError> {
         let player = &mut self.players[player as usize];
-        if let Some(worker) = player
+        // The least experienced worker is taken off, keeping the specialists at work.
+        let worker = player
             .workers
             .iter_mut()
-            .find(|w| w.current_action == WorkerAction::Gather(r))
-        {
-            worker.current_action = WorkerAction::Idle;
-            true
-        } else {
-            false
-        }
+            .filter(|w| w.current_action == WorkerAction::Gather(r))
+            .min_by_key(|w| w.get_experience(r))
+            .ok_or(ActionError::NoAllocatedWorker)?;
+        worker.current_action = WorkerAction::Idle;
+        Ok(())
     }
 
-    fn allocate_player_worker(&mut self, player: PlayerId, r: Resource) -> bool {
+    fn allocate_player_worker(&mut self, player: PlayerId, r: Resource) -> Result<(), ActionError> {
+        if !r.is_gatherable() {
+            return Err(ActionError::NotGatherable);
+        }
         let player = &mut self.players[player as usize];
-        if let Some(worker) = player
+        let worker = player
             .workers
             .iter_mut()
-            .find(|w| w.current_action == WorkerAction::Idle)
-        {
-            worker.current_action = WorkerAction::Gather(r);
-            true
-        } else {
-            false
-        }
+            .filter(|w| w.current_action == WorkerAction::Idle)
+            .max_by_key(|w| w.get_experience(r))
+            .ok_or(ActionError::NoIdleWorker)?;
+        worker.assign(r);
+        Ok(())
     }
 
     fn toggle_paused(&mut self) {
@@ -178,48 +733,676 @@ impl GameState {
         self.paused
     }
 
-    pub fn get_sell_trade(&self, item: SellItem) -> &Trade {
+    pub fn get_sell_trade(&self, item: SellItem) -> Trade {
         self.consumer_sector.get_trade(item)
     }
 
+    pub fn get_buy_trade(&self, item: BuyItem) -> Trade {
+        self.producer_sector.get_trade(item)
+    }
+
+    pub fn get_market(&self) -> &Market {
+        &self.market
+    }
+
+    pub fn get_deals(&self) -> &DealBook {
+        &self.deals
+    }
+
+    pub fn get_government(&self) -> &Government {
+        &self.government
+    }
+
+    /// Whether no more players can join, counting the seats of disconnected clients as free.
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= MAX_PLAYERS && self.vacant.is_empty()
+    }
+
+    /// Adds a player to the game and returns their id. Panics if no more players can be added.
     pub fn register_player(&mut self) -> PlayerId {
+        assert!(self.players.len() < MAX_PLAYERS, "the game is full");
+        self.record(JournalEvent::RegisterPlayer);
         let id = self.players.len() as PlayerId;
         self.players.push(Player::new(id));
+        self.api_actions_this_tick.push(0);
+        self.delayed_results.push(Vec::new());
         id
     }
 
-    fn produce(&mut self, player: PlayerId, item: ProductionItem) {
+    /// Seats a client that has just connected, in the lowest seat a disconnected client left, or
+    /// as a new player if there is none. The client takes over the player as it was left. Panics
+    /// if the game is full.
+    pub fn take_seat(&mut self) -> PlayerId {
+        match self.vacant.pop_first() {
+            Some(player) => {
+                self.delayed_results[player as usize].clear();
+                player
+            }
+            None => self.register_player(),
+        }
+    }
+
+    /// Frees the seat of a client that disconnected for the next client to take.
+    pub fn leave_seat(&mut self, player: PlayerId) {
+        self.vacant.insert(player);
+    }
+
+    /// The results of the player's delayed actions applied since the last call, oldest first.
+    pub fn take_delayed_results(&mut self, player: PlayerId) -> Vec<DelayedResult> {
+        std::mem::take(&mut self.delayed_results[player as usize])
+    }
+
+    fn produce(&mut self, player: PlayerId, item: ProductionItem) -> Result<(), ActionError> {
+        let player = self.get_player_mut(player);
+        if !player
+            .get_buildings()
+            .any(|b| b.get_kind() == item.get_building() && b.is_finished())
+        {
+            return Err(ActionError::NoBuilding);
+        }
+        if item.get_training().iter().any(|&ticks| ticks > 0) && player.workers.is_empty() {
+            return Err(ActionError::NoWorker);
+        }
+        if !player.get_stockpile_mut().consume(&item.get_cost()) {
+            return Err(ActionError::InsufficientResources);
+        }
+        player.enqueue_production(item, false);
+        Ok(())
+    }
+
+    /// Takes a queued item out of production and refunds its cost, whatever progress was made.
+    fn cancel_production(&mut self, player: PlayerId, id: ProductionId) -> Result<(), ActionError> {
+        let player = self.get_player_mut(player);
+        let queued = player
+            .cancel_production(id)
+            .ok_or(ActionError::UnknownProduction)?;
+        player.get_stockpile_mut().add(&queued.item.get_cost());
+        Ok(())
+    }
+
+    fn move_production(
+        &mut self,
+        player: PlayerId,
+        id: ProductionId,
+        position: usize,
+    ) -> Result<(), ActionError> {
+        if !self.get_player_mut(player).move_production(id, position) {
+            return Err(ActionError::UnknownProduction);
+        }
+        Ok(())
+    }
+
+    fn repeat_production(
+        &mut self,
+        player: PlayerId,
+        id: ProductionId,
+        repeat: bool,
+    ) -> Result<(), ActionError> {
+        if !self.get_player_mut(player).set_repeat(id, repeat) {
+            return Err(ActionError::UnknownProduction);
+        }
+        Ok(())
+    }
+
+    fn build(&mut self, player: PlayerId, kind: BuildingKind) -> Result<(), ActionError> {
+        if !kind.is_buildable() {
+            return Err(ActionError::NotBuildable);
+        }
+        let player = self.get_player_mut(player);
+        if !player.get_stockpile_mut().consume(&kind.get_cost()) {
+            return Err(ActionError::InsufficientResources);
+        }
+        player.add_building(Building::new(kind));
+        Ok(())
+    }
+
+    /// Moves an idle worker into the finished building of `kind` with the fewest staff.
+    fn staff_building(&mut self, player: PlayerId, kind: BuildingKind) -> Result<(), ActionError> {
+        let player = self.get_player_mut(player);
+        let finished = |b: &Building| b.get_kind() == kind && b.is_finished();
+        if !player.get_buildings().any(finished) {
+            return Err(ActionError::NoBuilding);
+        }
+        let (id, _) = player
+            .get_buildings()
+            .zip(0..)
+            .filter(|(b, _)| finished(b))
+            .map(|(_, id): (_, BuildingId)| (id, player.get_staff(id)))
+            .filter(|(_, staff)| *staff < kind.get_slots())
+            .min_by_key(|(_, staff)| *staff)
+            .ok_or(ActionError::NoFreeSlot)?;
+        let worker = player
+            .workers
+            .iter_mut()
+            .find(|w| w.current_action == WorkerAction::Idle)
+            .ok_or(ActionError::NoIdleWorker)?;
+        worker.current_action = WorkerAction::Staff(id);
+        Ok(())
+    }
+
+    /// Sends a worker home from the building of `kind` with the most staff.
+    fn unstaff_building(
+        &mut self,
+        player: PlayerId,
+        kind: BuildingKind,
+    ) -> Result<(), ActionError> {
+        let player = self.get_player_mut(player);
+        let (id, _) = player
+            .get_buildings()
+            .zip(0..)
+            .filter(|(b, _)| b.get_kind() == kind)
+            .map(|(_, id): (_, BuildingId)| (id, player.get_staff(id)))
+            .filter(|(_, staff)| *staff > 0)
+            .max_by_key(|(_, staff)| *staff)
+            .ok_or(ActionError::NoStaff)?;
+        let worker = player
+            .workers
+            .iter_mut()
+            .find(|w| w.current_action == WorkerAction::Staff(id))
+            .ok_or(ActionError::NoStaff)?;
+        worker.current_action = WorkerAction::Idle;
+        Ok(())
+    }
+
+    fn refresh_npc_orders(&mut self) {
+        let fills = self.consumer_sector.refresh_orders(&mut self.market);
+        self.settle(&fills);
+        let fills = self.producer_sector.refresh_orders(&mut self.market);
+        self.settle(&fills);
+        let fills = self
+            .government
+            .refresh_bids(&mut self.market, &self.consumer_sector);
+        self.settle(&fills);
+    }
+
+    fn settle(&mut self, fills: &[Fill]) {
+        for fill in fills {
+            let value = fill.price * fill.quantity as u64;
+            match fill.buyer {
+                Participant::Player(id) => {
+                    let player = &mut self.players[id as usize];
+                    *player.get_stockpile_mut().get_mut(fill.resource) += fill.quantity;
+                    player.add_money((fill.buyer_limit - fill.price) * fill.quantity as u64);
+                }
+                Participant::Consumer => {
+                    self.consumer_sector
+                        .record_trade(fill.resource, fill.quantity, &mut self.rng)
+                }
+                Participant::Government => self.government.record_purchase(fill),
+                Participant::Producer => (),
+            }
+            match fill.seller {
+                Participant::Player(id) => {
+                    // Sales to the NPC sectors and the government are taxed, trades between
+                    // players charged a fee.
+                    let levy = match fill.buyer {
+                        Participant::Player(_) => Levy::TransactionFee,
+                        _ => Levy::SalesTax,
+                    };
+                    let proceeds = self.government.levy(levy, value);
+                    self.players[id as usize].add_money(proceeds);
+                }
+                Participant::Producer => {
+                    self.producer_sector
+                        .record_trade(fill.resource, fill.quantity, &mut self.rng)
+                }
+                Participant::Consumer | Participant::Government => (),
+            }
+        }
+    }
+
+    /// Pays for an exploration, which adds a deposit of `resource` of random size once it is done.
+    fn explore(&mut self, player: PlayerId, resource: Resource) -> Result<(), ActionError> {
+        if !resource.is_gatherable() {
+            return Err(ActionError::NotGatherable);
+        }
+        let deposits = content::get().get_deposits();
         let player = self.get_player_mut(player);
-        if player.get_stockpile_mut().consume(&item.get_cost()) {
-            player.enqueue_production(item, item.get_production_time());
+        if !player.spend_money(deposits.explore_cost) {
+            return Err(ActionError::InsufficientMoney);
         }
+        player.start_exploration(resource, deposits.explore_time);
+        Ok(())
     }
 
-    fn sell(&mut self, player: PlayerId, item: SellItem) {
-        let Self {
-            ref mut consumer_sector,
-            ref mut players,
-            ..
-        } = self;
-        let player = &mut players[player as usize];
-        if let Some(money) = consumer_sector.trade(player.get_stockpile_mut(), item) {
-            player.add_money(money);
+    fn sell(&mut self, player: PlayerId, item: SellItem) -> Result<(), ActionError> {
+        let resource = item.get_resource();
+        let quantity = item.get_batch_size();
+        let stockpile = self.players[player as usize].get_stockpile_mut();
+        if stockpile.get(resource) < quantity {
+            return Err(ActionError::InsufficientResources);
         }
+        *stockpile.get_mut(resource) -= quantity;
+
+        let request = OrderRequest {
+            resource,
+            side: Side::Sell,
+            price: 0,
+            quantity,
+        };
+        let (_, fills, unfilled) = self
+            .market
+            .submit(Participant::Player(player), request, false);
+        *self.players[player as usize]
+            .get_stockpile_mut()
+            .get_mut(resource) += unfilled;
+        if fills.is_empty() {
+            return Err(ActionError::NoBuyers);
+        }
+        self.settle(&fills);
+        Ok(())
     }
 
-    pub fn handle_action(&mut self, action: GameAction) {
+    fn buy(&mut self, player: PlayerId, item: BuyItem) -> Result<(), ActionError> {
+        let resource = item.get_resource();
+        let quote = self
+            .market
+            .get_book(resource)
+            .quote_buy(item.get_batch_size())
+            .ok_or(ActionError::NoSellers)?;
+        let cost = quote.cost.ok_or(ActionError::InsufficientMoney)?;
+        if !self.players[player as usize].spend_money(cost) {
+            return Err(ActionError::InsufficientMoney);
+        }
+
+        let request = OrderRequest {
+            resource,
+            side: Side::Buy,
+            price: quote.worst_price,
+            quantity: quote.quantity,
+        };
+        let (_, mut fills, _) = self
+            .market
+            .submit(Participant::Player(player), request, false);
+        // The quote already charged the exact price of every fill, so there is nothing to refund.
+        for fill in fills.iter_mut() {
+            fill.buyer_limit = fill.price;
+        }
+        self.settle(&fills);
+        Ok(())
+    }
+
+    fn place_order(&mut self, player: PlayerId, request: OrderRequest) -> Result<(), ActionError> {
+        if request.quantity == 0 {
+            return Err(ActionError::InvalidOrder);
+        }
+        let p = &mut self.players[player as usize];
+        match request.side {
+            Side::Buy => {
+                let escrow = request
+                    .price
+                    .checked_mul(request.quantity as u64)
+                    .ok_or(ActionError::InsufficientMoney)?;
+                if !p.spend_money(escrow) {
+                    return Err(ActionError::InsufficientMoney);
+                }
+            }
+            Side::Sell => {
+                let stock = p.get_stockpile_mut().get_mut(request.resource);
+                if *stock < request.quantity {
+                    return Err(ActionError::InsufficientResources);
+                }
+                *stock -= request.quantity;
+            }
+        }
+        let (_, fills, _) = self
+            .market
+            .submit(Participant::Player(player), request, true);
+        self.settle(&fills);
+        Ok(())
+    }
+
+    fn cancel_order(&mut self, player: PlayerId, id: OrderId) -> Result<(), ActionError> {
+        let (resource, order) = self
+            .market
+            .cancel(Participant::Player(player), id)
+            .ok_or(ActionError::UnknownOrder)?;
+        let p = &mut self.players[player as usize];
+        match order.side {
+            Side::Buy => p.add_money(order.price * order.quantity as u64),
+            Side::Sell => *p.get_stockpile_mut().get_mut(resource) += order.quantity,
+        }
+        Ok(())
+    }
+
+    /// Cancels the player's resting orders and withdraws the offers they made, returning what was
+    /// held for them, so that a bankruptcy can seize it.
+    fn release_escrow(&mut self, player: PlayerId) {
+        let orders: Vec<OrderId> = self
+            .market
+            .orders_of(Participant::Player(player))
+            .map(|(_, order)| order.id)
+            .collect();
+        for id in orders {
+            let _ = self.cancel_order(player, id);
+        }
+        let offers: Vec<DealId> = self
+            .deals
+            .offers_of(player)
+            .filter(|offer| offer.from == player)
+            .map(|offer| offer.id)
+            .collect();
+        for id in offers {
+            let _ = self.decline_deal(player, id);
+        }
+    }
+
+    /// Offers `deal` to player `to`. What the proposer gives is held until the offer is accepted
+    /// or declined.
+    fn propose_deal(
+        &mut self,
+        from: PlayerId,
+        to: PlayerId,
+        deal: Deal,
+    ) -> Result<(), ActionError> {
+        if to == from || !deal.is_valid() {
+            return Err(ActionError::InvalidDeal);
+        }
+        if to as usize >= self.players.len() {
+            return Err(ActionError::UnknownPlayer);
+        }
+        deal.get_escrow()
+            .take_from(&mut self.players[from as usize])?;
+        self.deals.propose(from, to, deal);
+        Ok(())
+    }
+
+    fn accept_deal(&mut self, player: PlayerId, id: DealId) -> Result<(), ActionError> {
+        let offer = *self
+            .deals
+            .get_offer(id)
+            .filter(|offer| offer.to == player)
+            .ok_or(ActionError::UnknownDeal)?;
+        match offer.deal {
+            Deal::Exchange { give, receive } => {
+                receive.take_from(&mut self.players[player as usize])?;
+                // The fee on the money of each side is taken from what the other side receives.
+                for (mut bundle, to) in [(receive, offer.from), (give, player)] {
+                    bundle.money = self.government.levy(Levy::TransactionFee, bundle.money);
+                    bundle.give_to(&mut self.players[to as usize]);
+                }
+            }
+            Deal::Contract(side, terms) => self.deals.start_contract(&offer, side, terms),
+        }
+        self.deals.take_offer(id);
+        Ok(())
+    }
+
+    /// Takes the offer `id` off the table and returns what its proposer paid up front.
+    fn decline_deal(&mut self, player: PlayerId, id: DealId) -> Result<(), ActionError> {
+        if !self
+            .deals
+            .get_offer(id)
+            .is_some_and(|offer| offer.to == player || offer.from == player)
+        {
+            return Err(ActionError::UnknownDeal);
+        }
+        let offer = self.deals.take_offer(id).unwrap();
+        offer
+            .deal
+            .get_escrow()
+            .give_to(&mut self.players[offer.from as usize]);
+        Ok(())
+    }
+
+    fn counter_deal(
+        &mut self,
+        player: PlayerId,
+        id: DealId,
+        deal: Deal,
+    ) -> Result<(), ActionError> {
+        let from = self
+            .deals
+            .get_offer(id)
+            .filter(|offer| offer.to == player)
+            .ok_or(ActionError::UnknownDeal)?
+            .from;
+        if !deal.is_valid() {
+            return Err(ActionError::InvalidDeal);
+        }
+        // The counter-offer is checked before the original one goes, so a rejected counter-offer
+        // leaves everything as it was.
+        deal.get_escrow()
+            .take_from(&mut self.players[player as usize])?;
+        self.decline_deal(player, id)?;
+        self.deals.propose(player, from, deal);
+        Ok(())
+    }
+
+    /// Makes one delivery on every contract. A delivery happens only if the supplier has the goods
+    /// and the buyer the money; otherwise whoever falls short pays the other the penalty.
+    fn deliver_contracts(&mut self) {
+        for contract in self.deals.contracts_mut() {
+            let terms = contract.terms;
+            let supplier = contract.supplier as usize;
+            let buyer = contract.buyer as usize;
+            let delivered =
+                self.players[supplier].get_stockpile().get(terms.resource) >= terms.quantity;
+            let paid = self.players[buyer].get_money() >= terms.price;
+            if delivered && paid {
+                *self.players[supplier]
+                    .get_stockpile_mut()
+                    .get_mut(terms.resource) -= terms.quantity;
+                *self.players[buyer]
+                    .get_stockpile_mut()
+                    .get_mut(terms.resource) += terms.quantity;
+                self.players[buyer].spend_money(terms.price);
+                let proceeds = self.government.levy(Levy::TransactionFee, terms.price);
+                self.players[supplier].add_money(proceeds);
+            } else {
+                contract.defaults += 1;
+                for (debtor, creditor, defaulted) in
+                    [(supplier, buyer, !delivered), (buyer, supplier, !paid)]
+                {
+                    if defaulted {
+                        let fine = terms.penalty.min(self.players[debtor].get_money());
+                        self.players[debtor].spend_money(fine);
+                        self.players[creditor].add_money(fine);
+                    }
+                }
+            }
+            contract.remaining -= 1;
+        }
+        self.deals.remove_finished();
+    }
+
+    fn take_loan(
+        &mut self,
+        player: PlayerId,
+        amount: u64,
+        periods: u32,
+    ) -> Result<(), ActionError> {
+        if amount == 0 {
+            return Err(ActionError::InvalidAmount);
+        }
+        if periods == 0 || periods > content::get().get_bank().max_periods {
+            return Err(ActionError::InvalidTerm);
+        }
+        let tick = self.tick;
+        let player = self.get_player_mut(player);
+        if amount > player.get_credit_available(tick) {
+            return Err(ActionError::CreditLimit);
+        }
+        player.get_account_mut().borrow(amount, periods);
+        player.add_money(amount);
+        Ok(())
+    }
+
+    /// Pays off up to `amount` of loan `id` ahead of schedule.
+    fn repay_loan(&mut self, player: PlayerId, id: LoanId, amount: u64) -> Result<(), ActionError> {
+        if amount == 0 {
+            return Err(ActionError::InvalidAmount);
+        }
+        let player = self.get_player_mut(player);
+        if player.get_money() < amount {
+            return Err(ActionError::InsufficientMoney);
+        }
+        let paid = player
+            .get_account_mut()
+            .repay(id, amount)
+            .ok_or(ActionError::UnknownLoan)?;
+        player.spend_money(paid);
+        Ok(())
+    }
+
+    fn charge_api_action(&mut self, player: PlayerId) -> Result<(), ActionError> {
+        let restrictions = self.policy.api;
+        let actions = &mut self.api_actions_this_tick[player as usize];
+        if restrictions
+            .actions_per_tick
+            .is_some_and(|limit| *actions >= limit)
+        {
+            return Err(ActionError::RateLimited);
+        }
+        let player = &mut self.players[player as usize];
+        if player.get_money() < restrictions.money_fee
+            || !player
+                .get_stockpile_mut()
+                .consume(&restrictions.resource_fee)
+        {
+            return Err(ActionError::CannotAffordFee);
+        }
+        player.spend_money(restrictions.money_fee);
+        *actions += 1;
+        Ok(())
+    }
+
+    /// Applies the restrictions of the current `ActionPolicy` before handling the action. Actions
+    /// from the API may be delayed, in which case they are handled at the start of a later step.
+    pub fn submit_action(
+        &mut self,
+        action: GameAction,
+        source: ActionSource,
+    ) -> Result<ActionOutcome, ActionError> {
+        self.record(JournalEvent::Submit { action, source });
+        self.dispatch_action(action, source, None)
+    }
+
+    /// Dispatches `action`, which rule `rule` of its player took if given.
+    fn dispatch_action(
+        &mut self,
+        action: GameAction,
+        source: ActionSource,
+        rule: Option<usize>,
+    ) -> Result<ActionOutcome, ActionError> {
+        let player = action.get_player();
+        self.policy.check_source(source, player.is_none())?;
+        if source == ActionSource::Api {
+            let player = player.ok_or(ActionError::NotPermitted)?;
+            if player as usize >= self.players.len() {
+                return Err(ActionError::UnknownPlayer);
+            }
+            self.charge_api_action(player)?;
+            let delay = self.policy.api.delay.ticks;
+            if delay > 0 {
+                let until_tick = self.tick + delay;
+                self.delayed_actions.push(DelayedAction {
+                    until_tick,
+                    action,
+                    rule,
+                });
+                return Ok(ActionOutcome::Delayed { until_tick });
+            }
+        }
+        self.apply_action(action).map(|()| ActionOutcome::Applied)
+    }
+
+    /// Applies `action` directly, bypassing the action policy.
+    pub fn handle_action(&mut self, action: GameAction) -> Result<(), ActionError> {
+        self.record(JournalEvent::Handle(action));
+        self.apply_action(action)
+    }
+
+    fn apply_action(&mut self, action: GameAction) -> Result<(), ActionError> {
+        if let Some(player) = action.get_player() {
+            if player as usize >= self.players.len() {
+                return Err(ActionError::UnknownPlayer);
+            }
+        }
         match action {
             GameAction::AllocateWorker(player, resource) => {
-                self.allocate_player_worker(player, resource);
+                self.allocate_player_worker(player, resource)
             }
             GameAction::DeallocateWorker(player, resource) => {
-                self.deallocate_player_worker(player, resource);
+                self.deallocate_player_worker(player, resource)
             }
             GameAction::TogglePause => {
                 self.toggle_paused();
+                Ok(())
             }
             GameAction::Produce(player, item) => self.produce(player, item),
             GameAction::Sell(player, item) => self.sell(player, item),
+            GameAction::Buy(player, item) => self.buy(player, item),
+            GameAction::PlaceOrder(player, request) => self.place_order(player, request),
+            GameAction::CancelOrder(player, id) => self.cancel_order(player, id),
+            GameAction::Explore(player, resource) => self.explore(player, resource),
+            GameAction::Build(player, kind) => self.build(player, kind),
+            GameAction::StaffBuilding(player, kind) => self.staff_building(player, kind),
+            GameAction::UnstaffBuilding(player, kind) => self.unstaff_building(player, kind),
+            GameAction::CancelProduction(player, id) => self.cancel_production(player, id),
+            GameAction::MoveProduction(player, id, position) => {
+                self.move_production(player, id, position)
+            }
+            GameAction::RepeatProduction(player, id, repeat) => {
+                self.repeat_production(player, id, repeat)
+            }
+            GameAction::AddRule(player, rule) => {
+                self.get_player_mut(player).add_rule(rule);
+                Ok(())
+            }
+            GameAction::RemoveRule(player, index) => {
+                self.get_player_mut(player)
+                    .remove_rule(index)
+                    .ok_or(ActionError::UnknownRule)?;
+                // What the rule still had waiting is dropped, and later rules move up.
+                self.delayed_actions.retain(|delayed| {
+                    delayed.action.get_player() != Some(player) || delayed.rule != Some(index)
+                });
+                for delayed in self.delayed_actions.iter_mut() {
+                    if delayed.action.get_player() == Some(player) {
+                        if let Some(rule) = delayed.rule.as_mut().filter(|rule| **rule > index) {
+                            *rule -= 1;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            GameAction::ToggleRule(player, index) => {
+                if !self.get_player_mut(player).toggle_rule(index) {
+                    return Err(ActionError::UnknownRule);
+                }
+                Ok(())
+            }
+            GameAction::ProposeDeal(player, to, deal) => self.propose_deal(player, to, deal),
+            GameAction::AcceptDeal(player, id) => self.accept_deal(player, id),
+            GameAction::DeclineDeal(player, id) => self.decline_deal(player, id),
+            GameAction::CounterDeal(player, id, deal) => self.counter_deal(player, id, deal),
+            GameAction::TakeLoan(player, amount, periods) => {
+                self.take_loan(player, amount, periods)
+            }
+            GameAction::RepayLoan(player, id, amount) => self.repay_loan(player, id, amount),
+            GameAction::DepositMoney(player, amount) => {
+                if amount == 0 {
+                    return Err(ActionError::InvalidAmount);
+                }
+                let player = self.get_player_mut(player);
+                if !player.spend_money(amount) {
+                    return Err(ActionError::InsufficientMoney);
+                }
+                player.get_account_mut().add_deposit(amount);
+                Ok(())
+            }
+            GameAction::WithdrawMoney(player, amount) => {
+                if amount == 0 {
+                    return Err(ActionError::InvalidAmount);
+                }
+                let player = self.get_player_mut(player);
+                if !player.get_account_mut().withdraw(amount) {
+                    return Err(ActionError::InsufficientMoney);
+                }
+                player.add_money(amount);
+                Ok(())
+            }
         }
     }
 }
@@ -232,3 +1415,320 @@ impl fmt::Display for GameState {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deal::Bundle;
+    use crate::rules::{Condition, RuleAction};
+
+    /// Plays `ticks` ticks of a game with two players who keep trading, and returns the state.
+    fn play(seed: u64, ticks: u64) -> GameState {
+        let mut state = GameState::new(seed);
+        let players = [state.register_player(), state.register_player()];
+        for player in players {
+            for resource in [Resource::Iron, Resource::Copper, Resource::Stone] {
+                state
+                    .handle_action(GameAction::AllocateWorker(player, resource))
+                    .unwrap();
+            }
+        }
+        for tick in 0..ticks {
+            for (i, player) in players.into_iter().enumerate() {
+                let item =
+                    [SellItem::Iron, SellItem::Copper, SellItem::Stone][(tick as usize + i) % 3];
+                let actions = [
+                    GameAction::Sell(player, item),
+                    GameAction::Buy(player, BuyItem::Stone),
+                    GameAction::PlaceOrder(
+                        player,
+                        OrderRequest {
+                            resource: item.get_resource(),
+                            side: Side::Sell,
+                            price: 5 + tick % 7,
+                            quantity: 3,
+                        },
+                    ),
+                ];
+                let _ = state.submit_action(actions[tick as usize % 3], ActionSource::Api);
+            }
+            state.step();
+        }
+        state
+    }
+
+    #[test]
+    fn same_seed_and_actions_give_the_same_state() {
+        let first = serde_json::to_string(&play(42, 600)).unwrap();
+        let second = serde_json::to_string(&play(42, 600)).unwrap();
+        assert!(first == second, "the games diverged");
+        let other = serde_json::to_string(&play(43, 600)).unwrap();
+        assert!(first != other, "the seed had no effect");
+    }
+
+    fn tool_order(side: Side, price: u64, quantity: u32) -> OrderRequest {
+        OrderRequest {
+            resource: Resource::Tool,
+            side,
+            price,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn orders_hold_escrow_until_filled_or_cancelled() {
+        let mut state = GameState::new(0);
+        let buyer = state.register_player();
+        let seller = state.register_player();
+        state.get_player_mut(buyer).add_money(2000);
+        *state
+            .get_player_mut(seller)
+            .get_stockpile_mut()
+            .get_mut(Resource::Tool) = 10;
+        let tools = |state: &GameState, player| {
+            state.get_player(player).get_stockpile().get(Resource::Tool)
+        };
+
+        // The bid outbids the consumer sector and holds its full price.
+        let bid = tool_order(Side::Buy, 100, 10);
+        state
+            .handle_action(GameAction::PlaceOrder(buyer, bid))
+            .unwrap();
+        assert_eq!(state.get_player(buyer).get_money(), 1000);
+
+        // A lower ask trades at the resting bid's price.
+        state
+            .handle_action(GameAction::PlaceOrder(
+                seller,
+                tool_order(Side::Sell, 80, 5),
+            ))
+            .unwrap();
+        assert_eq!(tools(&state, buyer), 5);
+        assert_eq!(tools(&state, seller), 5);
+        let fee = state.government.get_collected(Levy::TransactionFee);
+        assert_eq!(state.get_player(seller).get_money() + fee, 500);
+        assert_eq!(state.get_player(buyer).get_money(), 1000);
+
+        // Cancelling the rest of the bid returns the money held for it.
+        let (_, order) = state
+            .get_market()
+            .orders_of(Participant::Player(buyer))
+            .next()
+            .unwrap();
+        let id = order.id;
+        state
+            .handle_action(GameAction::CancelOrder(buyer, id))
+            .unwrap();
+        assert_eq!(state.get_player(buyer).get_money(), 1500);
+        assert!(state
+            .handle_action(GameAction::CancelOrder(buyer, id))
+            .is_err());
+
+        // A higher bid buys a resting ask at the ask's price and gets the difference back.
+        state
+            .handle_action(GameAction::PlaceOrder(
+                seller,
+                tool_order(Side::Sell, 90, 2),
+            ))
+            .unwrap();
+        assert_eq!(tools(&state, seller), 3);
+        state
+            .handle_action(GameAction::PlaceOrder(buyer, tool_order(Side::Buy, 120, 2)))
+            .unwrap();
+        assert_eq!(state.get_player(buyer).get_money(), 1320);
+        assert_eq!(tools(&state, buyer), 7);
+
+        // An ask that does not fill holds its resources until cancelled.
+        state
+            .handle_action(GameAction::PlaceOrder(
+                seller,
+                tool_order(Side::Sell, 95, 3),
+            ))
+            .unwrap();
+        assert_eq!(tools(&state, seller), 0);
+        let (_, order) = state
+            .get_market()
+            .orders_of(Participant::Player(seller))
+            .next()
+            .unwrap();
+        let id = order.id;
+        state
+            .handle_action(GameAction::CancelOrder(seller, id))
+            .unwrap();
+        assert_eq!(tools(&state, seller), 3);
+        assert!(state
+            .handle_action(GameAction::PlaceOrder(
+                buyer,
+                tool_order(Side::Buy, 1000, 2)
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn buying_pays_the_asks_it_takes() {
+        let mut state = GameState::new(0);
+        let buyer = state.register_player();
+        let seller = state.register_player();
+        assert_eq!(
+            state.handle_action(GameAction::Buy(buyer, BuyItem::Stone)),
+            Err(ActionError::InsufficientMoney)
+        );
+
+        // A player undercutting the producer sector is bought from first, at their own price.
+        let batch = BuyItem::Stone.get_batch_size();
+        let producer_price = state.producer_sector.get_unit_price(BuyItem::Stone);
+        *state
+            .get_player_mut(seller)
+            .get_stockpile_mut()
+            .get_mut(Resource::Stone) = 2;
+        let ask = OrderRequest {
+            resource: Resource::Stone,
+            side: Side::Sell,
+            price: producer_price - 1,
+            quantity: 2,
+        };
+        state
+            .handle_action(GameAction::PlaceOrder(seller, ask))
+            .unwrap();
+        state.get_player_mut(buyer).add_money(10_000);
+        state
+            .handle_action(GameAction::Buy(buyer, BuyItem::Stone))
+            .unwrap();
+        let cost = 2 * (producer_price - 1) + (batch - 2) as u64 * producer_price;
+        assert_eq!(state.get_player(buyer).get_money(), 10_000 - cost);
+        let stock = state.get_player(buyer).get_stockpile().get(Resource::Stone);
+        assert_eq!(stock, batch);
+        let fee = state.government.get_collected(Levy::TransactionFee);
+        assert_eq!(
+            state.get_player(seller).get_money() + fee,
+            2 * (producer_price - 1)
+        );
+    }
+
+    #[test]
+    fn delayed_results_are_reported() {
+        let mut state = GameState::new(0);
+        let mut policy = ActionPolicy::default();
+        policy.api.delay.ticks = 2;
+        state.set_policy(policy);
+        let player = state.register_player();
+        let failing = GameAction::DeallocateWorker(player, Resource::Iron);
+        let outcome = state.submit_action(failing, ActionSource::Api).unwrap();
+        assert_eq!(outcome, ActionOutcome::Delayed { until_tick: 2 });
+        let working = GameAction::AllocateWorker(player, Resource::Iron);
+        state.submit_action(working, ActionSource::Api).unwrap();
+
+        state.step();
+        state.step();
+        assert!(state.take_delayed_results(player).is_empty());
+        state.step();
+        let results = state.take_delayed_results(player);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].tick, 2);
+        assert_eq!(results[0].result, Err(ActionError::NoAllocatedWorker));
+        assert_eq!(results[1].result, Ok(()));
+        assert!(state.take_delayed_results(player).is_empty());
+    }
+
+    #[test]
+    fn rules_wait_for_their_delayed_actions() {
+        let mut state = GameState::new(0);
+        let mut policy = ActionPolicy::default();
+        policy.api.delay.ticks = 3;
+        state.set_policy(policy);
+        let player = state.register_player();
+        let rule = Rule {
+            condition: Condition::Always,
+            action: RuleAction::Sell(SellItem::Iron),
+            enabled: true,
+        };
+        state
+            .handle_action(GameAction::AddRule(player, rule))
+            .unwrap();
+
+        state.step();
+        assert!(state.is_rule_pending(player, 0));
+        assert_eq!(state.delayed_actions.len(), 1);
+        state.step();
+        state.step();
+        assert_eq!(state.delayed_actions.len(), 1);
+        state.step();
+        // With no Iron to sell the action failed, and the rule took it again.
+        let p = state.get_player(player);
+        assert_eq!(
+            p.get_rule_error(0),
+            Some(ActionError::InsufficientResources)
+        );
+        assert!(state.is_rule_pending(player, 0));
+        assert!(state.take_delayed_results(player).is_empty());
+
+        state
+            .handle_action(GameAction::RemoveRule(player, 0))
+            .unwrap();
+        assert!(state.delayed_actions.is_empty());
+    }
+
+    #[test]
+    fn bankruptcy_seizes_escrow() {
+        let mut state = GameState::new(0);
+        let borrower = state.register_player();
+        let lender = state.register_player();
+        state
+            .handle_action(GameAction::TakeLoan(borrower, 1000, 20))
+            .unwrap();
+        let money = state.get_player(borrower).get_money();
+        let request = OrderRequest {
+            resource: Resource::Iron,
+            side: Side::Buy,
+            price: 1,
+            quantity: (money / 2) as u32,
+        };
+        state
+            .handle_action(GameAction::PlaceOrder(borrower, request))
+            .unwrap();
+        let give = Bundle {
+            money: state.get_player(borrower).get_money(),
+            ..Default::default()
+        };
+        let deal = Deal::Exchange {
+            give,
+            receive: Bundle::default(),
+        };
+        state
+            .handle_action(GameAction::ProposeDeal(borrower, lender, deal))
+            .unwrap();
+        assert_eq!(state.get_player(borrower).get_money(), 0);
+
+        while state.get_player(borrower).get_account().get_bankruptcies() == 0 {
+            assert!(state.get_tick() < 100 * state.get_pay_period());
+            state.step();
+        }
+        let market = state.get_market();
+        assert_eq!(market.orders_of(Participant::Player(borrower)).count(), 0);
+        assert_eq!(state.get_deals().offers_of(borrower).count(), 0);
+        // The escrow went to the bank instead of back to the borrower once the debt was gone.
+        assert_eq!(state.get_player(borrower).get_money(), 0);
+        assert_eq!(state.get_player(lender).get_money(), 0);
+    }
+
+    #[test]
+    fn training_needs_a_worker() {
+        let mut state = GameState::new(0);
+        let player = state.register_player();
+        let p = state.get_player_mut(player);
+        p.add_building(Building::finished(BuildingKind::Workshop));
+        p.get_stockpile_mut()
+            .add(&ProductionItem::TrainIron.get_cost());
+        p.workers.clear();
+        assert_eq!(
+            state.handle_action(GameAction::Produce(player, ProductionItem::TrainIron)),
+            Err(ActionError::NoWorker)
+        );
+        // Nothing was taken for the training that was refused.
+        let tools = state.get_player(player).get_stockpile().get(Resource::Tool);
+        assert_eq!(
+            tools,
+            ProductionItem::TrainIron.get_cost().get(Resource::Tool)
+        );
+    }
+}
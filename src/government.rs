@@ -0,0 +1,160 @@
+use std::fmt;
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::content;
+use crate::market::{Fill, Market, OrderId, OrderRequest, Participant, Side};
+use crate::npc::{ConsumerSector, NpcItem};
+use crate::sell::SellItem;
+
+/// The ways the government raises money.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Sequence, Serialize)]
+pub enum Levy {
+    /// Taken from what players receive for selling to the NPC sectors or the government.
+    SalesTax,
+    /// Taken from the money that changes hands between players, in the market or in deals.
+    TransactionFee,
+    /// Taken every upkeep period from the value of a player's stockpile.
+    PropertyTax,
+}
+
+impl fmt::Display for Levy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Levy::SalesTax => write!(f, "Sales tax"),
+            Levy::TransactionFee => write!(f, "Transaction fees"),
+            Levy::PropertyTax => write!(f, "Property tax"),
+        }
+    }
+}
+
+impl Levy {
+    /// The rate of the levy, in percent.
+    pub fn get_rate(&self) -> u32 {
+        let government = content::get().get_government();
+        match self {
+            Levy::SalesTax => government.sales_tax,
+            Levy::TransactionFee => government.transaction_fee,
+            Levy::PropertyTax => government.property_tax,
+        }
+    }
+}
+
+/// The government collects taxes and fees into its treasury and spends them on public works: it
+/// keeps bids in the market for the resources they use, a little above the price the consumer
+/// sector pays, and the resources it buys are used up.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Government {
+    treasury: u64,
+    collected: [u64; enum_iterator::cardinality::<Levy>()],
+    /// The hundredths of a unit of money each levy is owed beyond what it has taken.
+    owed: [u64; enum_iterator::cardinality::<Levy>()],
+    spent: u64,
+    bids: Vec<OrderId>,
+}
+
+impl Government {
+    /// Money in the treasury, not counting what is held for resting bids.
+    pub fn get_treasury(&self) -> u64 {
+        self.treasury
+    }
+
+    /// Everything raised by `levy` since the game began.
+    pub fn get_collected(&self, levy: Levy) -> u64 {
+        self.collected[levy as usize]
+    }
+
+    /// Everything spent on public works since the game began.
+    pub fn get_spent(&self) -> u64 {
+        self.spent
+    }
+
+    /// Takes `levy` out of `amount` and returns what is left. What a payment owes beyond a whole
+    /// unit of money is carried to the next payment of the same levy, so that many small payments
+    /// pay the same rate as one large one.
+    pub fn levy(&mut self, levy: Levy, amount: u64) -> u64 {
+        let owed = amount as u128 * levy.get_rate() as u128 + self.owed[levy as usize] as u128;
+        let tax = ((owed / 100) as u64).min(amount);
+        self.owed[levy as usize] = (owed - tax as u128 * 100) as u64;
+        self.collect(levy, tax);
+        amount - tax
+    }
+
+    pub fn collect(&mut self, levy: Levy, amount: u64) {
+        self.treasury += amount;
+        self.collected[levy as usize] += amount;
+    }
+
+    /// Replaces the bids from the previous tick, splitting the share of the treasury set aside
+    /// for public works between the resources they use, and returns any fills against resting
+    /// asks.
+    pub fn refresh_bids(&mut self, market: &mut Market, consumer: &ConsumerSector) -> Vec<Fill> {
+        for id in self.bids.drain(..) {
+            if let Some((_, order)) = market.cancel(Participant::Government, id) {
+                self.treasury += order.price * order.quantity as u64;
+            }
+        }
+        let government = content::get().get_government();
+        let total_weight: u64 = government.works.iter().map(|w| *w as u64).sum();
+        if total_weight == 0 {
+            return Vec::new();
+        }
+        let budget = self.treasury * government.spending as u64 / 100;
+        let mut fills = Vec::new();
+        for item in enum_iterator::all::<SellItem>() {
+            let resource = item.get_resource();
+            let weight = government.works.get(resource) as u64;
+            // The premium is rounded up, so that it still outbids the consumer sector at low prices.
+            let premium = 100 + government.premium as u64;
+            let price = (consumer.get_unit_price(item) * premium)
+                .div_ceil(100)
+                .max(1);
+            let quantity = (budget * weight / total_weight / price) as u32;
+            if quantity == 0 {
+                continue;
+            }
+            self.treasury -= price * quantity as u64;
+            let request = OrderRequest {
+                resource,
+                side: Side::Buy,
+                price,
+                quantity,
+            };
+            let (id, mut item_fills, _) = market.submit(Participant::Government, request, true);
+            self.bids.push(id);
+            fills.append(&mut item_fills);
+        }
+        fills
+    }
+
+    /// Pays for a fill of one of the government's bids, returning to the treasury what was held
+    /// above the price.
+    pub fn record_purchase(&mut self, fill: &Fill) {
+        self.treasury += (fill.buyer_limit - fill.price) * fill.quantity as u64;
+        self.spent += fill.price * fill.quantity as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_payments_pay_the_levy_rate() {
+        let mut government = Government::default();
+        let rate = Levy::SalesTax.get_rate() as u64;
+        let mut kept = 0;
+        for _ in 0..1000 {
+            kept += government.levy(Levy::SalesTax, 1);
+        }
+        let collected = government.get_collected(Levy::SalesTax);
+        assert_eq!(kept + collected, 1000);
+        assert!(collected.abs_diff(1000 * rate / 100) <= 1);
+
+        // Prices near the limit of the market do not overflow.
+        let amount = u64::MAX / 2;
+        let kept = government.levy(Levy::SalesTax, amount);
+        assert!(kept <= amount);
+    }
+}
@@ -4,10 +4,21 @@ use crate::visualization::TabType;
 pub enum InputAction {
     Quit,
     TogglePause,
+    QuickSave,
+    SpeedUp,
+    SlowDown,
+    StepOnce,
+    SeekBack,
+    SeekForward,
+    NextPlayer,
     MoveUp,
     MoveDown,
     Decrease,
     Increase,
+    Explore,
+    SwitchFocus,
+    CancelItem,
+    ToggleRepeat,
     SwitchTab(TabType),
     PerformAction,
 }
@@ -24,6 +35,17 @@ pub fn parse_input<R: Iterator<Item = Result<u8, io::Error>>>(r: &mut R) -> Opti
     match item {
         b'q' => Some(InputAction::Quit),
         b'p' => Some(InputAction::TogglePause),
+        b'w' => Some(InputAction::QuickSave),
+        b'+' | b'=' => Some(InputAction::SpeedUp),
+        b'-' => Some(InputAction::SlowDown),
+        b'n' => Some(InputAction::StepOnce),
+        b'[' => Some(InputAction::SeekBack),
+        b']' => Some(InputAction::SeekForward),
+        b'v' => Some(InputAction::NextPlayer),
+        b'e' => Some(InputAction::Explore),
+        b'c' => Some(InputAction::CancelItem),
+        b'l' => Some(InputAction::ToggleRepeat),
+        9 => Some(InputAction::SwitchFocus),
         13 => Some(InputAction::PerformAction),
         27 => parse_escaped(r),
         _ => None,
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::game_state::{GameAction, GameState};
+use crate::policy::ActionSource;
+use crate::save::{read_save, write_save, SaveError};
+
+/// Something that changed the game from the outside. Stepping is not recorded; the tick of each
+/// entry says how many steps came before it.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum JournalEvent {
+    RegisterPlayer,
+    Submit {
+        action: GameAction,
+        source: ActionSource,
+    },
+    Handle(GameAction),
+    /// Written when recording stops, so that a replay runs to the end of the session.
+    End,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct JournalEntry {
+    pub tick: u64,
+    pub event: JournalEvent,
+}
+
+/// A journal file starts with the state recording began from, in the save format, followed by
+/// one entry per line.
+pub struct Journal {
+    writer: BufWriter<fs::File>,
+    tick: u64,
+}
+
+impl Journal {
+    pub fn create<P: AsRef<Path>>(path: P, state: &GameState) -> Result<Self, SaveError> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        write_save(state, &mut writer)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(Journal {
+            writer,
+            tick: state.get_tick(),
+        })
+    }
+
+    /// Recording is best effort: a journal that can no longer be written to must not stop the
+    /// game.
+    pub fn record(&mut self, tick: u64, event: JournalEvent) {
+        self.tick = tick;
+        let entry = JournalEntry { tick, event };
+        let _ = serde_json::to_writer(&mut self.writer, &entry)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"))
+            .and_then(|()| self.writer.flush());
+    }
+
+    pub fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        self.record(self.tick, JournalEvent::End);
+    }
+}
+
+impl fmt::Debug for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Journal").field("tick", &self.tick).finish()
+    }
+}
+
+/// Plays a journal back against the state it was recorded from. Seeking backwards starts over
+/// from the beginning, which gives the same result as the simulation is deterministic.
+pub struct Replay {
+    initial: String,
+    entries: Vec<JournalEntry>,
+    end_tick: u64,
+    state: GameState,
+    next: usize,
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SaveError> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        let initial = lines.next().unwrap_or_default().to_owned();
+        let state = read_save(&initial)?;
+        let entries = lines
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_str::<JournalEntry>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| SaveError::Malformed(e.to_string()))?;
+        let end_tick = entries.last().map_or(state.get_tick(), |entry| entry.tick);
+        let mut replay = Replay {
+            initial,
+            entries,
+            end_tick,
+            state,
+            next: 0,
+        };
+        replay.apply_due();
+        Ok(replay)
+    }
+
+    pub fn get_state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.state.get_tick()
+    }
+
+    pub fn get_end_tick(&self) -> u64 {
+        self.end_tick
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.get_tick() >= self.end_tick
+    }
+
+    /// Moves to `tick`, clamped to the recorded session.
+    pub fn seek(&mut self, tick: u64) {
+        let tick = tick.min(self.end_tick);
+        if tick < self.get_tick() {
+            self.state = read_save(&self.initial).expect("journal start was read before");
+            self.next = 0;
+            self.apply_due();
+        }
+        while self.get_tick() < tick {
+            self.state.step();
+            self.apply_due();
+        }
+    }
+
+    /// Applies the entries recorded during the current tick.
+    fn apply_due(&mut self) {
+        let tick = self.state.get_tick();
+        while let Some(entry) = self.entries.get(self.next) {
+            if entry.tick > tick {
+                break;
+            }
+            match entry.event {
+                JournalEvent::RegisterPlayer => {
+                    self.state.register_player();
+                }
+                JournalEvent::Submit { action, source } => {
+                    let _ = self.state.submit_action(action, source);
+                }
+                JournalEvent::Handle(action) => {
+                    let _ = self.state.handle_action(action);
+                }
+                JournalEvent::End => (),
+            }
+            self.next += 1;
+        }
+    }
+}
@@ -1,25 +1,174 @@
+mod ai;
+mod api;
+mod bank;
+mod batch;
+mod building;
+mod buy;
 mod client;
+mod clock;
+mod content;
+mod deal;
+mod deposit;
 mod game_state;
+mod government;
 mod input;
+mod journal;
+mod market;
+mod npc;
+mod options;
 mod player;
+mod policy;
 mod production;
+mod protocol;
 mod resource;
+mod rpc;
+mod rules;
+mod save;
+mod script;
 mod sell;
+mod server;
+mod strategy;
 mod visualization;
 
-use client::run_client;
-use game_state::GameAction;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use ai::{AiPlayers, AiSpec};
+use batch::run_batch;
+use client::{run_client, run_replay};
+use content::Content;
 use game_state::GameState;
-use resource::Resource;
+use journal::{Journal, Replay};
+use options::Options;
+use rpc::run_rpc_server;
+use save::load_game;
+use script::ScriptBot;
+use server::run_server;
+use strategy::{load_bot, StrategyKind};
+
+const DEFAULT_SAVE_PATH: &str = "quicksave.json";
 
 fn main() {
-    let mut state = GameState::new();
-    state.register_player();
-    state.register_player();
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(path) = options.content {
+        match Content::load(&path) {
+            Ok(content) => {
+                content::set(content);
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if options.batch {
+        let mut batch = options.batch_options;
+        if batch.bots.is_empty() {
+            batch.bots.push(StrategyKind::Gatherer);
+        }
+        let seed = options.seed.unwrap_or_else(rand::random);
+        if let Err(e) = run_batch(&batch, seed, options.policy) {
+            eprintln!("batch error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = options.replay {
+        match Replay::load(&path) {
+            Ok(replay) => run_replay(replay),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
 
-    for res in enum_iterator::all::<Resource>() {
-        state.handle_action(GameAction::AllocateWorker(1, res));
+    let mut state = match &options.load {
+        Some(path) => match load_game(path) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(2);
+            }
+        },
+        None => GameState::new(options.seed.unwrap_or_else(rand::random)),
+    };
+    state.set_policy(options.policy);
+    if let Some(path) = &options.record {
+        match Journal::create(path, &state) {
+            Ok(journal) => state.set_journal(journal),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(2);
+            }
+        }
     }
+    let state = Arc::new(Mutex::new(state));
 
-    run_client(&mut state, 0);
+    if let Some(addr) = options.server {
+        // On a server the AIs take the first seats and everyone connecting plays against them.
+        let ais = AiPlayers::attach(&mut state.lock().unwrap(), 0, &options.ai);
+        spawn_api(&state, options.api);
+        if let Err(e) = run_server(state.clone(), ais, addr.as_str()) {
+            state.lock().unwrap().end_journal();
+            eprintln!("server error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut ai = options.ai;
+    if ai.is_empty() && !options.no_ai {
+        ai.push(AiSpec::default());
+    }
+    let ais = {
+        // The human is player 0 and the AIs sit next to them. A loaded game keeps its players,
+        // registering only those that are missing.
+        let mut state = state.lock().unwrap();
+        if state.players().next().is_none() {
+            state.register_player();
+        }
+        AiPlayers::attach(&mut state, 1, &ai)
+    };
+    spawn_api(&state, options.api);
+
+    let bot = options.bot_script.map(|path| match load_bot(&path) {
+        Ok(program) => ScriptBot::new(Arc::new(program)),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    });
+
+    let save_path = options
+        .save
+        .or(options.load)
+        .unwrap_or_else(|| DEFAULT_SAVE_PATH.to_owned());
+    run_client(state.clone(), 0, ais, bot, save_path);
+    state.lock().unwrap().end_journal();
+}
+
+/// Starts the API server on `addr`, if given. The local players must be registered first, so
+/// that an API client cannot take their seats.
+fn spawn_api(state: &Arc<Mutex<GameState>>, addr: Option<String>) {
+    if let Some(addr) = addr {
+        let state = state.clone();
+        thread::spawn(move || {
+            if let Err(e) = run_rpc_server(state, addr.as_str()) {
+                eprintln!("api error: {}", e);
+            }
+        });
+    }
 }
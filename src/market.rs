@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::player::PlayerId;
+use crate::resource::Resource;
+
+pub type OrderId = u64;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Participant {
+    Player(PlayerId),
+    Consumer,
+    Producer,
+    Government,
+}
+
+impl fmt::Display for Participant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Participant::Player(id) => write!(f, "Player {}", id),
+            Participant::Consumer => write!(f, "Consumer"),
+            Participant::Producer => write!(f, "Producer"),
+            Participant::Government => write!(f, "Government"),
+        }
+    }
+}
+
+/// A limit order as submitted by a participant. `price` is per unit of `resource`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OrderRequest {
+    pub resource: Resource,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Order {
+    pub id: OrderId,
+    pub owner: Participant,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u32,
+}
+
+/// A match between two orders. The trade happens at the price of the resting order, so a buyer
+/// whose limit was higher than `price` is owed the difference for the escrow it put up.
+#[derive(Clone, Copy, Debug)]
+pub struct Fill {
+    pub resource: Resource,
+    pub buyer: Participant,
+    pub seller: Participant,
+    pub price: u64,
+    pub quantity: u32,
+    pub buyer_limit: u64,
+}
+
+/// The quantity available to buy at market, what it would cost in total, or `None` if more than
+/// any amount of money, and the highest price that would be paid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quote {
+    pub quantity: u32,
+    pub cost: Option<u64>,
+    pub worst_price: u64,
+}
+
+/// Bids are kept best (highest) first and asks best (lowest) first. Orders at the same price are
+/// kept in arrival order, which is also id order.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OrderBook {
+    bids: Vec<Order>,
+    asks: Vec<Order>,
+    last_price: Option<u64>,
+}
+
+impl OrderBook {
+    pub fn bids(&self) -> &[Order] {
+        &self.bids
+    }
+
+    pub fn asks(&self) -> &[Order] {
+        &self.asks
+    }
+
+    pub fn best_bid(&self) -> Option<u64> {
+        self.bids.first().map(|o| o.price)
+    }
+
+    pub fn best_ask(&self) -> Option<u64> {
+        self.asks.first().map(|o| o.price)
+    }
+
+    pub fn get_last_price(&self) -> Option<u64> {
+        self.last_price
+    }
+
+    pub fn mid_price(&self) -> Option<u64> {
+        Some((self.best_bid()? + self.best_ask()?) / 2)
+    }
+
+    /// What buying up to `quantity` units at market would cost right now, or `None` if nobody
+    /// sells.
+    pub fn quote_buy(&self, quantity: u32) -> Option<Quote> {
+        let mut remaining = quantity;
+        let mut cost = Some(0u64);
+        let mut worst = None;
+        for ask in self.asks.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let q = remaining.min(ask.quantity);
+            cost = cost.and_then(|cost| cost.checked_add(ask.price.checked_mul(q as u64)?));
+            remaining -= q;
+            worst = Some(ask.price);
+        }
+        worst.map(|worst_price| Quote {
+            quantity: quantity - remaining,
+            cost,
+            worst_price,
+        })
+    }
+
+    fn insert(&mut self, order: Order) {
+        let (orders, before): (_, fn(&Order, &Order) -> bool) = match order.side {
+            Side::Buy => (&mut self.bids, |new, old| new.price > old.price),
+            Side::Sell => (&mut self.asks, |new, old| new.price < old.price),
+        };
+        let pos = orders
+            .iter()
+            .position(|old| before(&order, old))
+            .unwrap_or(orders.len());
+        orders.insert(pos, order);
+    }
+
+    /// Matches `order` against the opposite side of the book. Whatever remains unfilled is added
+    /// to the book if `rest` is set, and returned as the second element otherwise.
+    fn submit(&mut self, resource: Resource, mut order: Order, rest: bool) -> (Vec<Fill>, u32) {
+        let mut fills = Vec::new();
+        let opposite = match order.side {
+            Side::Buy => &mut self.asks,
+            Side::Sell => &mut self.bids,
+        };
+        while order.quantity > 0 {
+            let Some(best) = opposite.first_mut() else {
+                break;
+            };
+            let crosses = match order.side {
+                Side::Buy => best.price <= order.price,
+                Side::Sell => best.price >= order.price,
+            };
+            if !crosses {
+                break;
+            }
+            let quantity = order.quantity.min(best.quantity);
+            let (buyer, seller, buyer_limit) = match order.side {
+                Side::Buy => (order.owner, best.owner, order.price),
+                Side::Sell => (best.owner, order.owner, best.price),
+            };
+            fills.push(Fill {
+                resource,
+                buyer,
+                seller,
+                price: best.price,
+                quantity,
+                buyer_limit,
+            });
+            self.last_price = Some(best.price);
+            order.quantity -= quantity;
+            best.quantity -= quantity;
+            if best.quantity == 0 {
+                opposite.remove(0);
+            }
+        }
+        if order.quantity > 0 && rest {
+            self.insert(order);
+            (fills, 0)
+        } else {
+            (fills, order.quantity)
+        }
+    }
+
+    fn cancel(&mut self, owner: Participant, id: OrderId) -> Option<Order> {
+        for orders in [&mut self.bids, &mut self.asks] {
+            if let Some(pos) = orders.iter().position(|o| o.id == id && o.owner == owner) {
+                return Some(orders.remove(pos));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Market {
+    books: [OrderBook; enum_iterator::cardinality::<Resource>()],
+    next_order_id: OrderId,
+}
+
+impl Market {
+    pub fn get_book(&self, resource: Resource) -> &OrderBook {
+        &self.books[resource as usize]
+    }
+
+    /// Submits an order, returning its id, the resulting fills and the unfilled quantity that was
+    /// not left resting in the book.
+    pub fn submit(
+        &mut self,
+        owner: Participant,
+        request: OrderRequest,
+        rest: bool,
+    ) -> (OrderId, Vec<Fill>, u32) {
+        let id = self.next_order_id;
+        self.next_order_id += 1;
+        let order = Order {
+            id,
+            owner,
+            side: request.side,
+            price: request.price,
+            quantity: request.quantity,
+        };
+        let (fills, unfilled) =
+            self.books[request.resource as usize].submit(request.resource, order, rest);
+        (id, fills, unfilled)
+    }
+
+    pub fn cancel(&mut self, owner: Participant, id: OrderId) -> Option<(Resource, Order)> {
+        enum_iterator::all::<Resource>().find_map(|resource| {
+            self.books[resource as usize]
+                .cancel(owner, id)
+                .map(|order| (resource, order))
+        })
+    }
+
+    pub fn orders_of(&self, owner: Participant) -> impl Iterator<Item = (Resource, &Order)> {
+        enum_iterator::all::<Resource>().flat_map(move |resource| {
+            let book = self.get_book(resource);
+            book.bids
+                .iter()
+                .chain(book.asks.iter())
+                .filter(move |o| o.owner == owner)
+                .map(move |o| (resource, o))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(side: Side, price: u64, quantity: u32) -> OrderRequest {
+        OrderRequest {
+            resource: Resource::Iron,
+            side,
+            price,
+            quantity,
+        }
+    }
+
+    fn quantities(orders: &[Order]) -> Vec<(u64, u32)> {
+        orders.iter().map(|o| (o.price, o.quantity)).collect()
+    }
+
+    #[test]
+    fn books_keep_price_then_time_priority() {
+        let mut market = Market::default();
+        for (owner, price) in [(0, 5), (1, 7), (2, 5), (3, 6)] {
+            market.submit(Participant::Player(owner), order(Side::Buy, price, 1), true);
+            market.submit(
+                Participant::Player(owner),
+                order(Side::Sell, price + 10, 1),
+                true,
+            );
+        }
+        let book = market.get_book(Resource::Iron);
+        let owners = |orders: &[Order]| orders.iter().map(|o| o.owner).collect::<Vec<_>>();
+        assert_eq!(
+            owners(book.bids()),
+            [1, 3, 0, 2].map(Participant::Player).to_vec()
+        );
+        assert_eq!(
+            owners(book.asks()),
+            [0, 2, 3, 1].map(Participant::Player).to_vec()
+        );
+        assert_eq!(book.best_bid(), Some(7));
+        assert_eq!(book.best_ask(), Some(15));
+        assert_eq!(book.mid_price(), Some(11));
+        assert_eq!(book.get_last_price(), None);
+    }
+
+    #[test]
+    fn orders_fill_at_the_resting_price_and_partially() {
+        let mut market = Market::default();
+        let seller = Participant::Player(0);
+        let buyer = Participant::Player(1);
+        market.submit(seller, order(Side::Sell, 4, 5), true);
+        market.submit(seller, order(Side::Sell, 6, 5), true);
+
+        let (_, fills, unfilled) = market.submit(buyer, order(Side::Buy, 10, 8), true);
+        assert_eq!(unfilled, 0);
+        let trades: Vec<_> = fills
+            .iter()
+            .map(|f| (f.price, f.quantity, f.buyer_limit))
+            .collect();
+        assert_eq!(trades, [(4, 5, 10), (6, 3, 10)]);
+        assert!(fills.iter().all(|f| f.buyer == buyer && f.seller == seller));
+        let book = market.get_book(Resource::Iron);
+        assert_eq!(quantities(book.asks()), [(6, 2)]);
+        assert!(book.bids().is_empty());
+        assert_eq!(book.get_last_price(), Some(6));
+
+        // What does not cross rests, or is handed back if the order may not rest.
+        let (_, fills, unfilled) = market.submit(buyer, order(Side::Buy, 5, 4), false);
+        assert!(fills.is_empty());
+        assert_eq!(unfilled, 4);
+        let (_, fills, unfilled) = market.submit(seller, order(Side::Sell, 5, 4), true);
+        assert!(fills.is_empty());
+        assert_eq!(unfilled, 0);
+        assert_eq!(
+            quantities(market.get_book(Resource::Iron).asks()),
+            [(5, 4), (6, 2)]
+        );
+    }
+
+    #[test]
+    fn quotes_walk_the_asks() {
+        let mut market = Market::default();
+        market.submit(Participant::Producer, order(Side::Sell, 3, 2), true);
+        market.submit(Participant::Producer, order(Side::Sell, 5, 2), true);
+        let book = market.get_book(Resource::Iron);
+        let quote = |quantity, cost, worst_price| Quote {
+            quantity,
+            cost: Some(cost),
+            worst_price,
+        };
+        assert_eq!(book.quote_buy(3), Some(quote(3, 11, 5)));
+        assert_eq!(book.quote_buy(10), Some(quote(4, 16, 5)));
+        assert_eq!(market.get_book(Resource::Copper).quote_buy(1), None);
+
+        // No amount of money pays for asks that add up to more than a `u64`.
+        market.submit(
+            Participant::Player(0),
+            order(Side::Sell, u64::MAX / 2, 3),
+            true,
+        );
+        let book = market.get_book(Resource::Iron);
+        assert_eq!(book.quote_buy(7).unwrap().cost, None);
+        assert_eq!(book.quote_buy(7).unwrap().quantity, 7);
+    }
+
+    #[test]
+    fn only_the_owner_cancels() {
+        let mut market = Market::default();
+        let (id, _, _) = market.submit(Participant::Player(0), order(Side::Buy, 5, 3), true);
+        assert!(market.cancel(Participant::Player(1), id).is_none());
+        assert_eq!(market.orders_of(Participant::Player(0)).count(), 1);
+        let (resource, cancelled) = market.cancel(Participant::Player(0), id).unwrap();
+        assert_eq!(resource, Resource::Iron);
+        assert_eq!((cancelled.price, cancelled.quantity), (5, 3));
+        assert_eq!(market.orders_of(Participant::Player(0)).count(), 0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::marker::PhantomData;
+
+use enum_iterator::Sequence;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+use crate::buy::BuyItem;
+use crate::market::{Fill, Market, OrderId, OrderRequest, Participant, Side};
+use crate::resource::Resource;
+use crate::sell::{SellItem, Trade};
+
+const BATCHES_PER_TICK: u32 = 5;
+const PRICE_RECOVERY_RATE: f64 = 0.002;
+
+/// Something an NPC sector trades in batches, on the side of the market given by `SIDE`.
+pub trait NpcItem: Copy + Sequence {
+    /// `Side::Buy` for the items the consumer sector buys, `Side::Sell` for those the producer
+    /// sector sells.
+    const SIDE: Side;
+
+    fn get_resource(&self) -> Resource;
+
+    /// The batch and price of the item in the content, which the sector's price recovers
+    /// towards.
+    fn get_default_trade(&self) -> Trade;
+
+    /// The position of the item among all items of its kind.
+    fn index(&self) -> usize;
+
+    fn get_batch_size(&self) -> u32 {
+        self.get_default_trade().give.get(self.get_resource())
+    }
+
+    fn get_default_unit_price(&self) -> f64 {
+        self.get_default_trade().receive as f64 / self.get_batch_size() as f64
+    }
+}
+
+/// The NPC side of the economy for the items `I`. The sector keeps an order in the market for
+/// every item, moving its price against itself as it trades and slowly recovering towards the
+/// default price while it does not.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NpcSector<I> {
+    unit_prices: Vec<f64>,
+    orders: Vec<OrderId>,
+    #[serde(skip)]
+    items: PhantomData<I>,
+}
+
+/// Buys the `SellItem`s, lowering its bids as it buys.
+pub type ConsumerSector = NpcSector<SellItem>;
+/// Sells the `BuyItem`s, raising its asks as it sells.
+pub type ProducerSector = NpcSector<BuyItem>;
+
+impl<I: NpcItem> NpcSector<I> {
+    fn participant() -> Participant {
+        match I::SIDE {
+            Side::Buy => Participant::Consumer,
+            Side::Sell => Participant::Producer,
+        }
+    }
+
+    pub fn get_unit_price(&self, item: I) -> u64 {
+        self.unit_prices[item.index()].round() as u64
+    }
+
+    /// The sector's current order for one batch of `item`. The sector receives the resources and
+    /// gives the money if it buys, and the other way round if it sells; either way `give` holds
+    /// the resources and `receive` the money.
+    pub fn get_trade(&self, item: I) -> Trade {
+        let give = item.get_default_trade().give;
+        Trade {
+            give,
+            receive: self.get_unit_price(item) * give.get(item.get_resource()) as u64,
+        }
+    }
+
+    /// Replaces the orders from the previous tick and returns any fills against resting orders
+    /// of others.
+    pub fn refresh_orders(&mut self, market: &mut Market) -> Vec<Fill> {
+        for id in self.orders.drain(..) {
+            market.cancel(Self::participant(), id);
+        }
+        let mut fills = Vec::new();
+        for item in enum_iterator::all::<I>() {
+            let default = item.get_default_unit_price();
+            let price = &mut self.unit_prices[item.index()];
+            *price += (default - *price) * PRICE_RECOVERY_RATE;
+
+            let request = OrderRequest {
+                resource: item.get_resource(),
+                side: I::SIDE,
+                price: self.get_unit_price(item),
+                quantity: item.get_batch_size() * BATCHES_PER_TICK,
+            };
+            let (id, mut item_fills, _) = market.submit(Self::participant(), request, true);
+            self.orders.push(id);
+            fills.append(&mut item_fills);
+        }
+        fills
+    }
+
+    /// Moves the price of the item made of `resource` after the sector traded `quantity` of it:
+    /// down after buying, up after selling, by a random amount in proportion to the batches.
+    pub fn record_trade<R: Rng>(&mut self, resource: Resource, quantity: u32, rng: &mut R) {
+        let Some(item) = enum_iterator::all::<I>().find(|i| i.get_resource() == resource) else {
+            return;
+        };
+        let drift = match I::SIDE {
+            Side::Buy => -0.01,
+            Side::Sell => 0.01,
+        };
+        let fraction = quantity as f64 / item.get_batch_size() as f64;
+        let val: f64 = Normal::new(drift, 0.01).unwrap().sample(rng);
+        let price = &mut self.unit_prices[item.index()];
+        *price *= (val * fraction).exp();
+    }
+}
+
+impl<I: NpcItem> Default for NpcSector<I> {
+    fn default() -> Self {
+        NpcSector {
+            unit_prices: enum_iterator::all::<I>()
+                .map(|item| item.get_default_unit_price())
+                .collect(),
+            orders: Vec::new(),
+            items: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::game_state::GameRng;
+
+    #[test]
+    fn prices_move_against_the_sector() {
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut consumer = ConsumerSector::default();
+        let mut producer = ProducerSector::default();
+        let bought = SellItem::Tool.get_batch_size();
+        let sold = BuyItem::Stone.get_batch_size();
+        for _ in 0..50 {
+            consumer.record_trade(Resource::Tool, bought, &mut rng);
+            producer.record_trade(Resource::Stone, sold, &mut rng);
+        }
+        let tool = SellItem::Tool.index();
+        let stone = BuyItem::Stone.index();
+        assert!(consumer.unit_prices[tool] < SellItem::Tool.get_default_unit_price());
+        assert!(producer.unit_prices[stone] > BuyItem::Stone.get_default_unit_price());
+    }
+
+    #[test]
+    fn orders_are_replaced_every_refresh() {
+        let mut market = Market::default();
+        let mut consumer = ConsumerSector::default();
+        let mut producer = ProducerSector::default();
+        for _ in 0..3 {
+            consumer.refresh_orders(&mut market);
+            producer.refresh_orders(&mut market);
+        }
+        let bids = market.orders_of(Participant::Consumer).count();
+        let asks = market.orders_of(Participant::Producer).count();
+        assert_eq!(bids, enum_iterator::cardinality::<SellItem>());
+        assert_eq!(asks, enum_iterator::cardinality::<BuyItem>());
+        let book = market.get_book(Resource::Stone);
+        assert_eq!(
+            book.best_bid(),
+            Some(consumer.get_unit_price(SellItem::Stone))
+        );
+        assert_eq!(
+            book.best_ask(),
+            Some(producer.get_unit_price(BuyItem::Stone))
+        );
+        assert!(market
+            .orders_of(Participant::Producer)
+            .all(|(_, order)| order.side == Side::Sell));
+    }
+}
@@ -0,0 +1,101 @@
+use std::{fmt, str::FromStr};
+
+use crate::ai::AiSpec;
+use crate::batch::BatchOptions;
+use crate::player::MAX_PLAYERS;
+use crate::policy::ActionPolicy;
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub server: Option<String>,
+    pub api: Option<String>,
+    pub content: Option<String>,
+    pub load: Option<String>,
+    pub save: Option<String>,
+    pub seed: Option<u64>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub ai: Vec<AiSpec>,
+    pub no_ai: bool,
+    pub bot_script: Option<String>,
+    pub batch: bool,
+    pub batch_options: BatchOptions,
+    pub policy: ActionPolicy,
+}
+
+#[derive(Debug)]
+pub enum OptionsError {
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnknownArgument(String),
+    TooManyPlayers,
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::MissingValue(arg) => write!(f, "missing value for {}", arg),
+            OptionsError::InvalidValue(arg, e) => write!(f, "invalid value for {}: {}", arg, e),
+            OptionsError::UnknownArgument(arg) => write!(f, "unknown argument {}", arg),
+            OptionsError::TooManyPlayers => {
+                write!(f, "a game has at most {} players", MAX_PLAYERS)
+            }
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: String) -> Result<T, OptionsError>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e: T::Err| OptionsError::InvalidValue(arg.to_owned(), e.to_string()))
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, OptionsError> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(OptionsError::MissingValue(arg.clone()));
+            match arg.as_str() {
+                "--server" => options.server = Some(value()?),
+                "--api" => options.api = Some(value()?),
+                "--content" => options.content = Some(value()?),
+                "--load" => options.load = Some(value()?),
+                "--save" => options.save = Some(value()?),
+                "--seed" => options.seed = Some(parse_value(&arg, value()?)?),
+                "--record" => options.record = Some(value()?),
+                "--replay" => options.replay = Some(value()?),
+                "--ai" => options.ai.push(parse_value(&arg, value()?)?),
+                "--no-ai" => options.no_ai = true,
+                "--bot-script" => options.bot_script = Some(value()?),
+                "--batch" => options.batch = true,
+                "--ticks" => options.batch_options.ticks = parse_value(&arg, value()?)?,
+                "--games" => options.batch_options.games = parse_value(&arg, value()?)?,
+                "--bot" => options
+                    .batch_options
+                    .bots
+                    .push(parse_value(&arg, value()?)?),
+                "--stats" => options.batch_options.output = Some(value()?),
+                "--stats-format" => options.batch_options.format = parse_value(&arg, value()?)?,
+                "--stats-every" => options.batch_options.every = parse_value(&arg, value()?)?,
+                "--mode" => options.policy.mode = parse_value(&arg, value()?)?,
+                "--api-rate" => {
+                    options.policy.api.actions_per_tick = Some(parse_value(&arg, value()?)?)
+                }
+                "--api-delay" => options.policy.api.delay.ticks = parse_value(&arg, value()?)?,
+                "--api-fee" => options.policy.api.money_fee = parse_value(&arg, value()?)?,
+                "--api-resource-fee" => {
+                    options.policy.api.resource_fee = parse_value(&arg, value()?)?
+                }
+                _ => return Err(OptionsError::UnknownArgument(arg)),
+            }
+        }
+        // The local human takes a seat next to the AIs.
+        if options.ai.len() >= MAX_PLAYERS || options.batch_options.bots.len() > MAX_PLAYERS {
+            return Err(OptionsError::TooManyPlayers);
+        }
+        Ok(options)
+    }
+}
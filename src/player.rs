@@ -1,38 +1,153 @@
-use std::{collections::VecDeque, fmt};
+use std::fmt;
 
-use crate::game_state::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::bank::{self, Account};
+use crate::building::{Building, BuildingId, BuildingKind, ProductionId, QueuedItem};
+use crate::content::{self, Upkeep};
+use crate::deposit::Deposit;
+use crate::game_state::{ActionError, Duration};
+use crate::government::Levy;
 use crate::production::ProductionItem;
 use crate::resource::Resource;
 use crate::resource::ResourceAmount;
+use crate::rules::Rule;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub enum WorkerAction {
     Gather(Resource),
+    Staff(BuildingId),
     Idle,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Worker {
     pub current_action: WorkerAction,
+    /// The number of pay periods in a row the worker has not been paid for.
+    pub unpaid: u32,
+    /// Ticks spent gathering each resource.
+    experience: ResourceAmount,
+    last_gathered: Option<Resource>,
+    /// Ticks left at half speed after moving to a different resource.
+    settling: u64,
+    /// Percent of a unit gathered towards the next one.
+    progress: u32,
 }
 
 impl Worker {
     pub fn new() -> Self {
         Worker {
             current_action: WorkerAction::Idle,
+            unpaid: 0,
+            experience: ResourceAmount::new(),
+            last_gathered: None,
+            settling: 0,
+            progress: 0,
+        }
+    }
+
+    pub fn get_experience(&self, resource: Resource) -> u32 {
+        self.experience.get(resource)
+    }
+
+    pub fn add_experience(&mut self, resource: Resource, ticks: u32) {
+        *self.experience.get_mut(resource) += ticks;
+    }
+
+    pub fn get_level(&self, resource: Resource) -> usize {
+        let experience = self.get_experience(resource);
+        content::get()
+            .get_skills()
+            .levels
+            .iter()
+            .take_while(|level| experience >= **level)
+            .count()
+    }
+
+    /// How much the worker gathers of `resource` per tick, in percent of a unit.
+    pub fn get_productivity(&self, resource: Resource) -> u32 {
+        let skills = content::get().get_skills();
+        let productivity = 100 + self.get_level(resource) as u32 * skills.bonus;
+        if self.settling > 0 {
+            productivity / 2
+        } else {
+            productivity
+        }
+    }
+
+    /// Sets the worker to gather `resource`. A worker that last gathered something else needs time
+    /// to settle in.
+    pub fn assign(&mut self, resource: Resource) {
+        if self.last_gathered.is_some_and(|last| last != resource) {
+            self.settling = content::get().get_skills().settle_in.ticks;
+        }
+        self.last_gathered = Some(resource);
+        self.current_action = WorkerAction::Gather(resource);
+    }
+
+    /// Works for one tick at a deposit giving `yield_percent` of the full yield, and returns what
+    /// was gathered, if anything.
+    fn gather(
+        &mut self,
+        tick: u64,
+        upkeep: &Upkeep,
+        yield_percent: u32,
+    ) -> Option<(Resource, u32)> {
+        let WorkerAction::Gather(resource) = self.current_action else {
+            return None;
+        };
+        if yield_percent == 0 || !self.works_on(tick, upkeep) {
+            return None;
+        }
+        self.progress += self.get_productivity(resource) * yield_percent / 100;
+        self.add_experience(resource, 1);
+        self.settling = self.settling.saturating_sub(1);
+        let units = self.progress / 100;
+        self.progress %= 100;
+        Some((resource, units))
+    }
+
+    pub fn get_wage(&self, upkeep: &Upkeep) -> u64 {
+        match self.current_action {
+            WorkerAction::Gather(_) | WorkerAction::Staff(_) => upkeep.wage,
+            WorkerAction::Idle => upkeep.idle_wage,
+        }
+    }
+
+    pub fn is_striking(&self, upkeep: &Upkeep) -> bool {
+        self.unpaid >= upkeep.strike_after
+    }
+
+    /// Whether the worker gathers or staffs its building during `tick`. Unpaid workers only work
+    /// every other tick, and not at all while on strike.
+    fn works_on(&self, tick: u64, upkeep: &Upkeep) -> bool {
+        match self.unpaid {
+            0 => true,
+            _ if self.is_striking(upkeep) => false,
+            _ => tick.is_multiple_of(2),
         }
     }
 }
 
 pub type PlayerId = u8;
 
-#[derive(Debug)]
+/// As many players as a `PlayerId` can tell apart.
+pub const MAX_PLAYERS: usize = PlayerId::MAX as usize + 1;
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Player {
     id: PlayerId,
     pub workers: Vec<Worker>,
     stockpile: ResourceAmount,
     money: u64,
-    production_queue: VecDeque<(ProductionItem, Duration)>,
+    buildings: Vec<Building>,
+    next_production_id: ProductionId,
+    deposits: Vec<Deposit>,
+    explorations: Vec<(Resource, Duration)>,
+    rules: Vec<Rule>,
+    /// Why the action of each rule last failed, if it did the last time it was taken.
+    rule_errors: Vec<Option<ActionError>>,
+    account: Account,
 }
 
 impl Player {
@@ -42,36 +157,376 @@ impl Player {
             workers: vec![Worker::new(), Worker::new(), Worker::new()],
             stockpile: ResourceAmount::new(),
             money: 0,
-            production_queue: VecDeque::new(),
-        }
-    }
-
-    pub fn step(&mut self) {
-        let completed_item = self
-            .production_queue
-            .front_mut()
-            .and_then(|production_item| {
-                let production_time = &mut production_item.1;
-                debug_assert!(production_time.ticks != 0);
-                production_time.ticks -= 1;
-                if production_time.ticks == 0 {
-                    Some(production_item.0)
-                } else {
-                    None
+            buildings: enum_iterator::all::<BuildingKind>()
+                .flat_map(|kind| {
+                    let start = content::get().get_building(kind).start;
+                    (0..start).map(move |_| Building::finished(kind))
+                })
+                .collect(),
+            next_production_id: 0,
+            deposits: Resource::gatherable()
+                .map(|res| Deposit::new(res, content::get().get_deposits().start))
+                .collect(),
+            explorations: Vec::new(),
+            rules: Vec::new(),
+            rule_errors: Vec::new(),
+            account: Account::default(),
+        }
+    }
+
+    pub fn step(&mut self, tick: u64) {
+        let upkeep = content::get().get_upkeep();
+        let mut staff = vec![0; self.buildings.len()];
+        for w in self.workers.iter() {
+            if let WorkerAction::Staff(id) = w.current_action {
+                if w.works_on(tick, upkeep) {
+                    staff[id as usize] += 1;
+                }
+            }
+        }
+        let completed_items: Vec<_> = self
+            .buildings
+            .iter_mut()
+            .zip(staff)
+            .zip(0..)
+            .filter_map(|((building, staff), id)| Some((id, building.step(staff)?)))
+            .collect();
+        for (building, queued) in completed_items {
+            queued.item.produce(self);
+            // A repeating item goes to the back of the queue for as long as it can be paid for.
+            if queued.repeat && self.stockpile.consume(&queued.item.get_cost()) {
+                self.buildings[building as usize].enqueue(queued.id, queued.item, true);
+            }
+        }
+
+        let gather_bonus = self.get_gather_bonus();
+        for w in self.workers.iter_mut() {
+            let deposits = &mut self.deposits;
+            let yield_percent = match w.current_action {
+                WorkerAction::Gather(res) => richest_deposit(deposits, res)
+                    .map_or(0, |d| d.get_yield() * (100 + gather_bonus) / 100),
+                WorkerAction::Staff(_) | WorkerAction::Idle => 0,
+            };
+            let gathered = w.gather(tick, upkeep, yield_percent);
+            if let Some((resource, mut units)) = gathered {
+                // Whatever the richest deposit cannot supply is taken from the next one.
+                while units > 0 {
+                    let Some(deposit) = richest_deposit(deposits, resource) else {
+                        break;
+                    };
+                    let taken = deposit.take(units);
+                    let stock = self.stockpile.get_mut(resource);
+                    *stock = stock.saturating_add(taken);
+                    units -= taken;
                 }
-            });
+            }
+        }
+        self.deposits.retain(|d| !d.is_exhausted());
+        self.account.accrue_interest();
+    }
+
+    /// How much of `resource` the player can keep.
+    pub fn get_capacity(&self, resource: Resource) -> u32 {
+        let base = content::get().get_resource(resource).capacity;
+        self.buildings
+            .iter()
+            .filter(|b| b.is_finished())
+            .fold(base, |capacity, b| {
+                capacity.saturating_add(b.get_kind().get_storage())
+            })
+    }
+
+    /// The percentage the player's working mines add to the yield of gathering workers.
+    pub fn get_gather_bonus(&self) -> u32 {
+        self.buildings
+            .iter()
+            .filter(|b| b.is_active())
+            .map(|b| b.get_kind().get_gather_bonus())
+            .sum()
+    }
+
+    pub fn get_buildings(&self) -> impl Iterator<Item = &Building> {
+        self.buildings.iter()
+    }
+
+    pub fn add_building(&mut self, building: Building) {
+        self.buildings.push(building);
+    }
 
-        if let Some(item) = completed_item {
-            self.production_queue.pop_front();
-            item.produce(self);
+    /// The number of workers staffing building `id`.
+    pub fn get_staff(&self, id: BuildingId) -> u32 {
+        self.workers
+            .iter()
+            .filter(|w| w.current_action == WorkerAction::Staff(id))
+            .count() as u32
+    }
+
+    /// Queues `item` in the finished building of its kind with the least production queued,
+    /// preferring maintained ones. Returns the id of the queued item, or None if the player has
+    /// no such building.
+    pub fn enqueue_production(
+        &mut self,
+        item: ProductionItem,
+        repeat: bool,
+    ) -> Option<ProductionId> {
+        let building = self
+            .buildings
+            .iter_mut()
+            .filter(|b| b.get_kind() == item.get_building() && b.is_finished())
+            .min_by_key(|b| (b.is_unpaid(), b.get_backlog()))?;
+        let id = self.next_production_id;
+        self.next_production_id += 1;
+        building.enqueue(id, item, repeat);
+        Some(id)
+    }
+
+    /// Everything queued in any of the player's buildings, with the building it is queued in.
+    pub fn get_production_queue(&self) -> impl Iterator<Item = (BuildingId, &QueuedItem)> {
+        self.buildings
+            .iter()
+            .zip(0..)
+            .flat_map(|(b, id)| b.get_queue().map(move |queued| (id, queued)))
+    }
+
+    /// Takes item `id` out of whichever queue it is in.
+    pub fn cancel_production(&mut self, id: ProductionId) -> Option<QueuedItem> {
+        self.buildings.iter_mut().find_map(|b| b.cancel(id))
+    }
+
+    /// Moves item `id` to `position` in the queue of its building. Returns false if no such item
+    /// is queued.
+    pub fn move_production(&mut self, id: ProductionId, position: usize) -> bool {
+        self.buildings
+            .iter_mut()
+            .any(|b| b.move_queued(id, position))
+    }
+
+    /// Sets whether item `id` is queued again every time it is finished. Returns false if no such
+    /// item is queued.
+    pub fn set_repeat(&mut self, id: ProductionId, repeat: bool) -> bool {
+        match self.buildings.iter_mut().find_map(|b| b.get_queued_mut(id)) {
+            Some(queued) => {
+                queued.repeat = repeat;
+                true
+            }
+            None => false,
         }
+    }
 
-        for w in self.workers.iter() {
-            match &w.current_action {
-                WorkerAction::Gather(r) => *self.stockpile.get_mut(*r) += 1,
-                WorkerAction::Idle => (),
+    /// Removes whatever exceeds the player's capacity from the stockpile and returns it.
+    pub fn take_overflow(&mut self) -> ResourceAmount {
+        let mut overflow = ResourceAmount::new();
+        for res in enum_iterator::all::<Resource>() {
+            let capacity = self.get_capacity(res);
+            let stock = self.stockpile.get_mut(res);
+            if *stock > capacity {
+                *overflow.get_mut(res) = *stock - capacity;
+                *stock = capacity;
+            }
+        }
+        overflow
+    }
+
+    /// Lets every perishable resource in the stockpile spoil by its decay rate.
+    pub fn spoil(&mut self) {
+        for res in enum_iterator::all::<Resource>() {
+            let decay = content::get().get_resource(res).decay as u64;
+            let stock = self.stockpile.get_mut(res);
+            *stock -= (*stock as u64 * decay / 100) as u32;
+        }
+    }
+
+    pub fn get_deposits(&self) -> impl Iterator<Item = &Deposit> {
+        self.deposits.iter()
+    }
+
+    pub fn add_deposit(&mut self, deposit: Deposit) {
+        self.deposits.push(deposit);
+    }
+
+    pub fn start_exploration(&mut self, resource: Resource, time: Duration) {
+        self.explorations.push((resource, time));
+    }
+
+    pub fn get_explorations(&self) -> impl Iterator<Item = &(Resource, Duration)> {
+        self.explorations.iter()
+    }
+
+    /// Advances every exploration by a tick and returns the resources of those that finished.
+    pub fn advance_explorations(&mut self) -> Vec<Resource> {
+        let mut finished = Vec::new();
+        self.explorations.retain_mut(|(resource, remaining)| {
+            remaining.ticks -= 1;
+            if remaining.ticks == 0 {
+                finished.push(*resource);
+            }
+            remaining.ticks > 0
+        });
+        finished
+    }
+
+    /// The player's automation rules, in the order they are evaluated.
+    pub fn get_rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn add_rule(&mut self, rule: Rule) {
+        self.rules.push(rule);
+        self.rule_errors.push(None);
+    }
+
+    pub fn remove_rule(&mut self, index: usize) -> Option<Rule> {
+        (index < self.rules.len()).then(|| {
+            self.rule_errors.remove(index);
+            self.rules.remove(index)
+        })
+    }
+
+    /// Why the action of rule `index` failed the last time it was taken, if it did.
+    pub fn get_rule_error(&self, index: usize) -> Option<ActionError> {
+        self.rule_errors.get(index).copied().flatten()
+    }
+
+    pub fn set_rule_error(&mut self, index: usize, error: Option<ActionError>) {
+        if let Some(slot) = self.rule_errors.get_mut(index) {
+            *slot = error;
+        }
+    }
+
+    /// Switches rule `index` on or off. Returns false if there is no such rule.
+    pub fn toggle_rule(&mut self, index: usize) -> bool {
+        match self.rules.get_mut(index) {
+            Some(rule) => {
+                rule.enabled = !rule.enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gives `ticks` of experience in `resource` to the least experienced worker gathering it, or
+    /// to the least experienced worker if nobody is.
+    pub fn train(&mut self, resource: Resource, ticks: u32) {
+        let worker = self.workers.iter_mut().min_by_key(|w| {
+            (
+                w.current_action != WorkerAction::Gather(resource),
+                w.get_experience(resource),
+            )
+        });
+        if let Some(worker) = worker {
+            worker.add_experience(resource, ticks);
+        }
+    }
+
+    /// Pays every worker that can be afforded, in order, and then the maintenance of every
+    /// finished building. Workers that cannot be paid miss the period; those that have missed too
+    /// many leave. Buildings that cannot be paid stop until the next period.
+    pub fn pay_wages(&mut self) {
+        let upkeep = content::get().get_upkeep();
+        for w in self.workers.iter_mut() {
+            let wage = w.get_wage(upkeep);
+            if self.money >= wage && self.stockpile.consume(&upkeep.food) {
+                self.money -= wage;
+                w.unpaid = 0;
+            } else {
+                w.unpaid += 1;
+            }
+        }
+        self.workers.retain(|w| w.unpaid < upkeep.desert_after);
+        for b in self.buildings.iter_mut().filter(|b| b.is_finished()) {
+            let maintenance = b.get_kind().get_maintenance();
+            let paid = self.money >= maintenance;
+            if paid {
+                self.money -= maintenance;
+            }
+            b.set_unpaid(!paid);
+        }
+    }
+
+    /// Pays the installments due on the player's loans. Returns whether the player has missed so
+    /// many that they go bankrupt.
+    pub fn pay_installments(&mut self) -> bool {
+        self.account.pay_installments(&mut self.money)
+    }
+
+    /// The bank takes the player's money, deposit and stock, cheapest resources last, until the
+    /// debt is covered, writes off whatever is left and will not lend to them for a while.
+    /// Anything the player holds in escrow must be returned to them first.
+    pub fn go_bankrupt(&mut self, tick: u64) {
+        let mut debt = self.account.get_debt();
+        let deposit = self.account.get_deposit();
+        self.account.withdraw(deposit);
+        let mut cash = self.money + deposit;
+        let paid = debt.min(cash);
+        debt -= paid;
+        cash -= paid;
+        self.money = cash;
+        let mut resources: Vec<_> = enum_iterator::all::<Resource>()
+            .map(|res| (res, bank::unit_value(res)))
+            .filter(|(_, value)| *value > 0)
+            .collect();
+        resources.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+        for (res, value) in resources {
+            if debt == 0 {
+                break;
             }
+            let stock = self.stockpile.get_mut(res);
+            let seized = (*stock as u64).min(debt.div_ceil(value)) as u32;
+            *stock -= seized;
+            debt = debt.saturating_sub(seized as u64 * value);
         }
+        self.account
+            .write_off(tick + content::get().get_bank().lockout.ticks);
+    }
+
+    /// The property tax due at the next payday: a share of the value of the stockpile.
+    pub fn get_property_tax(&self) -> u64 {
+        bank::stock_value(&self.stockpile) * Levy::PropertyTax.get_rate() as u64 / 100
+    }
+
+    pub fn get_account(&self) -> &Account {
+        &self.account
+    }
+
+    pub fn get_account_mut(&mut self) -> &mut Account {
+        &mut self.account
+    }
+
+    /// What the player owns, at the bank's prices, less what they owe it.
+    pub fn get_net_worth(&self) -> i64 {
+        let assets = self.money + self.account.get_deposit() + bank::stock_value(&self.stockpile);
+        assets as i64 - self.account.get_debt() as i64
+    }
+
+    /// How much more the bank will lend the player at `tick`.
+    pub fn get_credit_available(&self, tick: u64) -> u64 {
+        if self.account.is_locked(tick) {
+            return 0;
+        }
+        let bank = content::get().get_bank();
+        let worth = self.get_net_worth().max(0) as u64;
+        let limit = bank.base_credit + worth * bank.credit_percent as u64 / 100;
+        limit.saturating_sub(self.account.get_debt())
+    }
+
+    /// The money and food needed to pay every worker and maintain every finished building for
+    /// the coming period.
+    pub fn get_wage_bill(&self) -> (u64, ResourceAmount) {
+        let upkeep = content::get().get_upkeep();
+        let mut food = ResourceAmount::new();
+        let mut money = 0;
+        for w in self.workers.iter() {
+            money += w.get_wage(upkeep);
+            food.add(&upkeep.food);
+        }
+        for b in self.buildings.iter().filter(|b| b.is_finished()) {
+            money += b.get_kind().get_maintenance();
+        }
+        (money, food)
+    }
+
+    pub fn get_unpaid_workers(&self) -> usize {
+        self.workers.iter().filter(|w| w.unpaid > 0).count()
     }
 
     pub fn get_id(&self) -> PlayerId {
@@ -94,13 +549,21 @@ impl Player {
         self.money += amount
     }
 
-    pub fn enqueue_production(&mut self, item: ProductionItem, production_time: Duration) {
-        self.production_queue.push_back((item, production_time));
+    pub fn spend_money(&mut self, amount: u64) -> bool {
+        if self.money < amount {
+            return false;
+        }
+        self.money -= amount;
+        true
     }
+}
 
-    pub fn get_current_production(&self) -> Option<&(ProductionItem, Duration)> {
-        self.production_queue.front()
-    }
+/// The deposit of `resource` with the largest reserve left, which is the one workers gather from.
+fn richest_deposit(deposits: &mut [Deposit], resource: Resource) -> Option<&mut Deposit> {
+    deposits
+        .iter_mut()
+        .filter(|d| d.get_resource() == resource && !d.is_exhausted())
+        .max_by_key(|d| d.get_reserve())
 }
 
 impl fmt::Display for Player {
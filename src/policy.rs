@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::game_state::{ActionError, Duration};
+use crate::resource::ResourceAmount;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ActionSource {
+    Interactive,
+    Api,
+    /// A built-in AI opponent. It is held to the game mode no more than a human is, and pays no
+    /// API fees.
+    Ai,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum GameMode {
+    /// Players may act both interactively and through the API.
+    #[default]
+    Mixed,
+    /// Only interactive actions are accepted.
+    Manual,
+    /// Only API actions are accepted. Pausing is still possible interactively.
+    Automatic,
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mixed" => Ok(GameMode::Mixed),
+            "manual" => Ok(GameMode::Manual),
+            "automatic" => Ok(GameMode::Automatic),
+            _ => Err(format!("unknown game mode {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Drawbacks applied to every action submitted through the API.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct ApiRestrictions {
+    pub actions_per_tick: Option<u32>,
+    pub delay: Duration,
+    pub money_fee: u64,
+    pub resource_fee: ResourceAmount,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct ActionPolicy {
+    pub mode: GameMode,
+    pub api: ApiRestrictions,
+}
+
+impl ActionPolicy {
+    pub fn check_source(&self, source: ActionSource, is_meta: bool) -> Result<(), ActionError> {
+        match (self.mode, source) {
+            (GameMode::Manual, ActionSource::Api) => Err(ActionError::NotPermitted),
+            (GameMode::Automatic, ActionSource::Interactive) if !is_meta => {
+                Err(ActionError::NotPermitted)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,58 +1,66 @@
-use std::{convert::TryFrom, fmt};
+use std::fmt;
 
 use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
 
+use crate::building::BuildingKind;
+use crate::content;
 use crate::game_state::Duration;
 use crate::player::{Player, Worker};
-use crate::resource::Resource;
-use crate::resource::ResourceAmount;
+use crate::resource::{Resource, ResourceAmount};
 
-#[derive(Clone, Copy, Debug, Sequence)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Sequence, Serialize)]
 pub enum ProductionItem {
     WorkerIron = 0,
     WorkerStone = 1,
+    IronPlate = 2,
+    CopperWire = 3,
+    Brick = 4,
+    Tool = 5,
+    TrainIron = 6,
+    TrainCopper = 7,
+    TrainStone = 8,
 }
 
 impl ProductionItem {
     pub fn get_cost(&self) -> ResourceAmount {
-        match self {
-            ProductionItem::WorkerIron => {
-                let mut cost = ResourceAmount::new();
-                *cost.get_mut(Resource::Iron) = 100;
-                cost
-            }
-            ProductionItem::WorkerStone => {
-                let mut cost = ResourceAmount::new();
-                *cost.get_mut(Resource::Stone) = 100;
-                cost
-            }
-        }
+        content::get().get_recipe(*self).cost
     }
 
-    pub fn get_production_time(&self) -> Duration {
-        match self {
-            ProductionItem::WorkerIron => std::time::Duration::from_secs(8).into(),
-            ProductionItem::WorkerStone => std::time::Duration::from_secs(5).into(),
-        }
+    /// The resources added to the stockpile once the item is produced.
+    pub fn get_output(&self) -> ResourceAmount {
+        content::get().get_recipe(*self).output
     }
 
-    pub fn produce(&self, player: &mut Player) {
-        match self {
-            ProductionItem::WorkerIron | ProductionItem::WorkerStone => {
-                player.workers.push(Worker::new());
-            }
-        }
+    /// The number of workers added once the item is produced.
+    pub fn get_workers(&self) -> u32 {
+        content::get().get_recipe(*self).workers
     }
-}
 
-impl TryFrom<usize> for ProductionItem {
-    type Error = ();
+    /// The ticks of experience in each resource given to a worker once the item is produced.
+    pub fn get_training(&self) -> ResourceAmount {
+        content::get().get_recipe(*self).training
+    }
+
+    /// The kind of building the item is made in.
+    pub fn get_building(&self) -> BuildingKind {
+        content::get().get_recipe(*self).building
+    }
 
-    fn try_from(v: usize) -> Result<Self, Self::Error> {
-        match v {
-            0 => Ok(ProductionItem::WorkerIron),
-            1 => Ok(ProductionItem::WorkerStone),
-            _ => Err(()),
+    pub fn get_production_time(&self) -> Duration {
+        content::get().get_recipe(*self).time
+    }
+
+    pub fn produce(&self, player: &mut Player) {
+        player.get_stockpile_mut().add(&self.get_output());
+        for _ in 0..self.get_workers() {
+            player.workers.push(Worker::new());
+        }
+        let training = self.get_training();
+        for res in enum_iterator::all::<Resource>() {
+            if training.get(res) > 0 {
+                player.train(res, training.get(res));
+            }
         }
     }
 }
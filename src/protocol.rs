@@ -0,0 +1,597 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::bank::LoanId;
+use crate::building::{BuildingId, BuildingKind, ProductionId, QueuedItem};
+use crate::buy::BuyItem;
+use crate::deal::{Contract, TradeOffer};
+use crate::game_state::{ActionError, DelayedResult, GameAction, GameState};
+use crate::government::Levy;
+use crate::market::{Order, OrderBook, OrderId, Participant, Side};
+use crate::player::{Player, PlayerId, WorkerAction};
+use crate::production::ProductionItem;
+use crate::resource::{Resource, ResourceAmount};
+use crate::rules::Rule;
+use crate::sell::SellItem;
+
+pub const PROTOCOL_VERSION: u16 = 17;
+
+// Every frame is a big-endian u32 length followed by that many bytes: a big-endian u16
+// protocol version, a u8 encoding tag and the encoded message.
+const HEADER_LEN: u32 = 3;
+const MAX_FRAME_LEN: u32 = 1 << 24;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Json = 0,
+    Binary = 1,
+}
+
+impl TryFrom<u8> for Encoding {
+    type Error = ProtocolError;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(Encoding::Json),
+            1 => Ok(Encoding::Binary),
+            _ => Err(ProtocolError::UnknownEncoding(v)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    Io(io::Error),
+    UnsupportedVersion(u16),
+    UnknownEncoding(u8),
+    FrameTooLarge(u32),
+    Malformed(String),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Io(e) => write!(f, "i/o error: {}", e),
+            ProtocolError::UnsupportedVersion(v) => {
+                write!(f, "unsupported protocol version {}", v)
+            }
+            ProtocolError::UnknownEncoding(e) => write!(f, "unknown encoding {}", e),
+            ProtocolError::FrameTooLarge(len) => write!(f, "frame of {} bytes is too large", len),
+            ProtocolError::Malformed(e) => write!(f, "malformed message: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ProtocolError {
+    fn from(e: io::Error) -> Self {
+        ProtocolError::Io(e)
+    }
+}
+
+pub fn encode<M: Serialize>(encoding: Encoding, msg: &M) -> Result<Vec<u8>, ProtocolError> {
+    let payload = match encoding {
+        Encoding::Json => {
+            serde_json::to_vec(msg).map_err(|e| ProtocolError::Malformed(e.to_string()))
+        }
+        Encoding::Binary => {
+            bincode::serialize(msg).map_err(|e| ProtocolError::Malformed(e.to_string()))
+        }
+    }?;
+    let len = payload.len() as u32 + HEADER_LEN;
+    if len > MAX_FRAME_LEN {
+        return Err(ProtocolError::FrameTooLarge(len));
+    }
+    let mut frame = Vec::with_capacity(len as usize + 4);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    frame.push(encoding as u8);
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+pub fn write_message<W: Write, M: Serialize>(
+    w: &mut W,
+    encoding: Encoding,
+    msg: &M,
+) -> Result<(), ProtocolError> {
+    w.write_all(&encode(encoding, msg)?)?;
+    Ok(w.flush()?)
+}
+
+/// Reads one frame and decodes it. The whole frame is consumed even if decoding fails, so the
+/// stream stays usable after a `Malformed` or `UnsupportedVersion` error.
+pub fn read_message<R: Read, M: DeserializeOwned>(
+    r: &mut R,
+) -> Result<(Encoding, M), ProtocolError> {
+    let mut len = [0; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if !(HEADER_LEN..=MAX_FRAME_LEN).contains(&len) {
+        return Err(ProtocolError::FrameTooLarge(len));
+    }
+    let mut frame = vec![0; len as usize];
+    r.read_exact(&mut frame)?;
+
+    let version = u16::from_be_bytes([frame[0], frame[1]]);
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::UnsupportedVersion(version));
+    }
+    let encoding = Encoding::try_from(frame[2])?;
+    let payload = &frame[HEADER_LEN as usize..];
+    let msg = match encoding {
+        Encoding::Json => {
+            serde_json::from_slice(payload).map_err(|e| ProtocolError::Malformed(e.to_string()))
+        }
+        Encoding::Binary => {
+            bincode::deserialize(payload).map_err(|e| ProtocolError::Malformed(e.to_string()))
+        }
+    }?;
+    Ok((encoding, msg))
+}
+
+pub type ActionId = u64;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ClientMessage {
+    Hello,
+    Action { id: ActionId, action: GameAction },
+    RequestSnapshot,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ServerMessage {
+    Welcome {
+        player: PlayerId,
+    },
+    Ack {
+        id: ActionId,
+    },
+    Delayed {
+        id: ActionId,
+        until_tick: u64,
+    },
+    Rejected {
+        id: ActionId,
+        reason: ActionError,
+    },
+    /// A delayed action of the client's player has been applied, or has failed.
+    DelayedResult(DelayedResult),
+    Snapshot(GameSnapshot),
+    Delta(SnapshotDelta),
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResourceQuantity {
+    pub resource: Resource,
+    pub amount: u32,
+}
+
+fn quantities(amount: &ResourceAmount) -> Vec<ResourceQuantity> {
+    enum_iterator::all::<Resource>()
+        .map(|resource| ResourceQuantity {
+            resource,
+            amount: amount.get(resource),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProductionEntry {
+    pub id: ProductionId,
+    pub building: BuildingId,
+    pub item: ProductionItem,
+    pub remaining_ticks: u64,
+    pub repeat: bool,
+}
+
+fn production_entries<'a>(
+    queue: impl Iterator<Item = (BuildingId, &'a QueuedItem)>,
+) -> Vec<ProductionEntry> {
+    queue
+        .map(|(building, queued)| ProductionEntry {
+            id: queued.id,
+            building,
+            item: queued.item,
+            remaining_ticks: queued.remaining.ticks,
+            repeat: queued.repeat,
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DepositSnapshot {
+    pub resource: Resource,
+    pub reserve: u32,
+    pub initial: u32,
+    /// The percentage of a full yield workers currently gather from it.
+    pub yield_percent: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ExplorationEntry {
+    pub resource: Resource,
+    pub remaining_ticks: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BuildingSnapshot {
+    pub id: BuildingId,
+    pub kind: BuildingKind,
+    /// Ticks until the building is finished, or 0 once it is.
+    pub construction_ticks: u64,
+    /// Whether the building stands still because its maintenance went unpaid.
+    pub unpaid: bool,
+    pub staff: u32,
+    pub slots: u32,
+    pub queue: Vec<ProductionEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PlayerSnapshot {
+    pub id: PlayerId,
+    pub money: u64,
+    pub stockpile: Vec<ResourceQuantity>,
+    /// How much of each resource the player can store. Anything beyond is lost or sold off cheaply
+    /// at the end of the tick.
+    pub capacity: Vec<ResourceQuantity>,
+    pub idle_workers: u32,
+    pub gathering_workers: Vec<ResourceQuantity>,
+    /// Everything queued in any of the player's buildings.
+    pub production_queue: Vec<ProductionEntry>,
+    pub buildings: Vec<BuildingSnapshot>,
+    pub orders: Vec<OrderSnapshot>,
+    /// The money needed to pay every worker and maintain every finished building at the next
+    /// payday.
+    pub wages_due: u64,
+    /// Workers that missed their last payday and work less or not at all until paid.
+    pub unpaid_workers: u32,
+    /// The property tax due at the next payday on the value of the stockpile.
+    pub property_tax_due: u64,
+    pub deposits: Vec<DepositSnapshot>,
+    pub explorations: Vec<ExplorationEntry>,
+    /// The player's automation rules, in the order they are evaluated.
+    pub rules: Vec<Rule>,
+    /// The trade offers the player made or received, oldest first.
+    pub trade_offers: Vec<TradeOffer>,
+    /// The contracts the player supplies or buys under.
+    pub contracts: Vec<Contract>,
+    /// Money in the bank, earning interest.
+    pub deposit: u64,
+    pub loans: Vec<LoanSnapshot>,
+    /// How much more the bank will lend the player right now.
+    pub credit_available: u64,
+    pub bankruptcies: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LoanSnapshot {
+    pub id: LoanId,
+    /// Money owed, interest included.
+    pub balance: u64,
+    /// The money due at the next payday.
+    pub installment: u64,
+    pub periods_left: u32,
+    /// Installments missed in a row.
+    pub missed: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OrderSnapshot {
+    pub id: OrderId,
+    pub resource: Resource,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PriceLevel {
+    pub price: u64,
+    pub quantity: u32,
+}
+
+fn price_levels(orders: &[Order]) -> Vec<PriceLevel> {
+    let mut levels: Vec<PriceLevel> = Vec::new();
+    for order in orders {
+        match levels.last_mut() {
+            Some(level) if level.price == order.price => level.quantity += order.quantity,
+            _ => levels.push(PriceLevel {
+                price: order.price,
+                quantity: order.quantity,
+            }),
+        }
+    }
+    levels
+}
+
+/// The order book of one resource, aggregated by price. Bids and asks are both listed best first.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MarketSnapshot {
+    pub resource: Resource,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    pub last_price: Option<u64>,
+    pub mid_price: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TradeSnapshot {
+    pub item: SellItem,
+    pub give: Vec<ResourceQuantity>,
+    pub receive: u64,
+}
+
+/// What the producer sector currently asks for one batch of `item`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OfferSnapshot {
+    pub item: BuyItem,
+    pub receive: Vec<ResourceQuantity>,
+    pub cost: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RevenueSnapshot {
+    pub levy: Levy,
+    /// The rate of the levy, in percent.
+    pub rate: u32,
+    /// Everything raised by the levy since the game began.
+    pub collected: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GovernmentSnapshot {
+    /// Money in the treasury, not counting what is held for the bids of public works.
+    pub treasury: u64,
+    pub revenue: Vec<RevenueSnapshot>,
+    /// Everything spent on public works since the game began.
+    pub spent: u64,
+    /// The bids of public works currently resting in the market.
+    pub bids: Vec<OrderSnapshot>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GameSnapshot {
+    pub tick: u64,
+    pub paused: bool,
+    pub players: Vec<PlayerSnapshot>,
+    pub trades: Vec<TradeSnapshot>,
+    pub offers: Vec<OfferSnapshot>,
+    pub markets: Vec<MarketSnapshot>,
+    pub government: GovernmentSnapshot,
+}
+
+/// Changes since a previous snapshot. Players, trades, offers and markets are included in full if any of their
+/// fields changed, and omitted otherwise; the government is included in full if anything about it changed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnapshotDelta {
+    pub tick: u64,
+    pub paused: Option<bool>,
+    pub players: Vec<PlayerSnapshot>,
+    pub trades: Vec<TradeSnapshot>,
+    pub offers: Vec<OfferSnapshot>,
+    pub markets: Vec<MarketSnapshot>,
+    pub government: Option<GovernmentSnapshot>,
+}
+
+impl PlayerSnapshot {
+    pub fn new(p: &Player, state: &GameState) -> Self {
+        let market = state.get_market();
+        let deals = state.get_deals();
+        let account = p.get_account();
+        let count_workers = |action: WorkerAction| {
+            p.workers
+                .iter()
+                .filter(|w| w.current_action == action)
+                .count() as u32
+        };
+        PlayerSnapshot {
+            id: p.get_id(),
+            money: p.get_money(),
+            stockpile: quantities(p.get_stockpile()),
+            capacity: enum_iterator::all::<Resource>()
+                .map(|resource| ResourceQuantity {
+                    resource,
+                    amount: p.get_capacity(resource),
+                })
+                .collect(),
+            idle_workers: count_workers(WorkerAction::Idle),
+            gathering_workers: enum_iterator::all::<Resource>()
+                .map(|resource| ResourceQuantity {
+                    resource,
+                    amount: count_workers(WorkerAction::Gather(resource)),
+                })
+                .collect(),
+            production_queue: production_entries(p.get_production_queue()),
+            buildings: p
+                .get_buildings()
+                .zip(0..)
+                .map(|(b, id)| BuildingSnapshot {
+                    id,
+                    kind: b.get_kind(),
+                    construction_ticks: b.get_construction().ticks,
+                    unpaid: b.is_unpaid(),
+                    staff: p.get_staff(id),
+                    slots: b.get_kind().get_slots(),
+                    queue: production_entries(b.get_queue().map(|queued| (id, queued))),
+                })
+                .collect(),
+            orders: market
+                .orders_of(Participant::Player(p.get_id()))
+                .map(|(resource, order)| OrderSnapshot {
+                    id: order.id,
+                    resource,
+                    side: order.side,
+                    price: order.price,
+                    quantity: order.quantity,
+                })
+                .collect(),
+            wages_due: p.get_wage_bill().0,
+            unpaid_workers: p.get_unpaid_workers() as u32,
+            property_tax_due: p.get_property_tax(),
+            deposits: p
+                .get_deposits()
+                .map(|d| DepositSnapshot {
+                    resource: d.get_resource(),
+                    reserve: d.get_reserve(),
+                    initial: d.get_initial(),
+                    yield_percent: d.get_yield(),
+                })
+                .collect(),
+            explorations: p
+                .get_explorations()
+                .map(|(resource, duration)| ExplorationEntry {
+                    resource: *resource,
+                    remaining_ticks: duration.ticks,
+                })
+                .collect(),
+            rules: p.get_rules().to_vec(),
+            trade_offers: deals.offers_of(p.get_id()).copied().collect(),
+            contracts: deals.contracts_of(p.get_id()).copied().collect(),
+            deposit: account.get_deposit(),
+            loans: account
+                .get_loans()
+                .map(|loan| LoanSnapshot {
+                    id: loan.id,
+                    balance: loan.balance,
+                    installment: loan.get_installment(),
+                    periods_left: loan.periods_left,
+                    missed: loan.missed,
+                })
+                .collect(),
+            credit_available: p.get_credit_available(state.get_tick()),
+            bankruptcies: account.get_bankruptcies(),
+        }
+    }
+}
+
+impl TradeSnapshot {
+    pub fn current(state: &GameState) -> Vec<Self> {
+        enum_iterator::all::<SellItem>()
+            .map(|item| {
+                let trade = state.get_sell_trade(item);
+                TradeSnapshot {
+                    item,
+                    give: quantities(&trade.give),
+                    receive: trade.receive,
+                }
+            })
+            .collect()
+    }
+}
+
+impl OfferSnapshot {
+    pub fn current(state: &GameState) -> Vec<Self> {
+        enum_iterator::all::<BuyItem>()
+            .map(|item| {
+                let trade = state.get_buy_trade(item);
+                OfferSnapshot {
+                    item,
+                    receive: quantities(&trade.give),
+                    cost: trade.receive,
+                }
+            })
+            .collect()
+    }
+}
+
+impl MarketSnapshot {
+    pub fn new(resource: Resource, book: &OrderBook) -> Self {
+        MarketSnapshot {
+            resource,
+            bids: price_levels(book.bids()),
+            asks: price_levels(book.asks()),
+            last_price: book.get_last_price(),
+            mid_price: book.mid_price(),
+        }
+    }
+
+    pub fn current(state: &GameState) -> Vec<Self> {
+        enum_iterator::all::<Resource>()
+            .map(|resource| MarketSnapshot::new(resource, state.get_market().get_book(resource)))
+            .collect()
+    }
+}
+
+impl GovernmentSnapshot {
+    pub fn new(state: &GameState) -> Self {
+        let government = state.get_government();
+        GovernmentSnapshot {
+            treasury: government.get_treasury(),
+            revenue: enum_iterator::all::<Levy>()
+                .map(|levy| RevenueSnapshot {
+                    levy,
+                    rate: levy.get_rate(),
+                    collected: government.get_collected(levy),
+                })
+                .collect(),
+            spent: government.get_spent(),
+            bids: state
+                .get_market()
+                .orders_of(Participant::Government)
+                .map(|(resource, order)| OrderSnapshot {
+                    id: order.id,
+                    resource,
+                    side: order.side,
+                    price: order.price,
+                    quantity: order.quantity,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl GameSnapshot {
+    pub fn new(state: &GameState) -> Self {
+        GameSnapshot {
+            tick: state.get_tick(),
+            paused: state.is_paused(),
+            players: state
+                .players()
+                .map(|p| PlayerSnapshot::new(p, state))
+                .collect(),
+            trades: TradeSnapshot::current(state),
+            offers: OfferSnapshot::current(state),
+            markets: MarketSnapshot::current(state),
+            government: GovernmentSnapshot::new(state),
+        }
+    }
+
+    pub fn delta_from(&self, previous: &GameSnapshot) -> SnapshotDelta {
+        SnapshotDelta {
+            tick: self.tick,
+            paused: (self.paused != previous.paused).then_some(self.paused),
+            players: self
+                .players
+                .iter()
+                .filter(|p| !previous.players.contains(p))
+                .cloned()
+                .collect(),
+            trades: self
+                .trades
+                .iter()
+                .filter(|t| !previous.trades.contains(t))
+                .cloned()
+                .collect(),
+            offers: self
+                .offers
+                .iter()
+                .filter(|o| !previous.offers.contains(o))
+                .cloned()
+                .collect(),
+            markets: self
+                .markets
+                .iter()
+                .filter(|m| !previous.markets.contains(m))
+                .cloned()
+                .collect(),
+            government: (self.government != previous.government).then(|| self.government.clone()),
+        }
+    }
+}
@@ -1,20 +1,29 @@
-use std::{
-    convert::{TryFrom, TryInto},
-    fmt,
-};
+use std::{fmt, str::FromStr};
 
 use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Sequence, PartialEq)]
+use crate::content;
+
+#[derive(Clone, Copy, Debug, Deserialize, Sequence, PartialEq, Serialize)]
 pub enum Resource {
     Iron = 0,
     Copper = 1,
     Stone = 2,
+    IronPlate = 3,
+    CopperWire = 4,
+    Brick = 5,
+    Tool = 6,
 }
 
 impl Resource {
-    pub const fn count() -> usize {
-        (Resource::Stone as usize) + 1
+    /// Gatherable resources are gathered by workers, the others can only be produced.
+    pub fn is_gatherable(&self) -> bool {
+        content::get().get_resource(*self).gatherable
+    }
+
+    pub fn gatherable() -> impl Iterator<Item = Resource> {
+        enum_iterator::all::<Self>().filter(|res| res.is_gatherable())
     }
 
     pub fn names() -> impl Iterator<Item = String> {
@@ -22,16 +31,13 @@ impl Resource {
     }
 }
 
-impl TryFrom<usize> for Resource {
-    type Error = ();
+impl FromStr for Resource {
+    type Err = String;
 
-    fn try_from(v: usize) -> Result<Self, Self::Error> {
-        match v {
-            0 => Ok(Resource::Iron),
-            1 => Ok(Resource::Copper),
-            2 => Ok(Resource::Stone),
-            _ => Err(()),
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        enum_iterator::all::<Resource>()
+            .find(|res| res.to_string() == s)
+            .ok_or_else(|| format!("unknown resource {}", s))
     }
 }
 
@@ -41,7 +47,7 @@ impl fmt::Display for Resource {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct ResourceAmount {
     res: [u32; enum_iterator::cardinality::<Resource>()],
 }
@@ -63,6 +69,13 @@ impl ResourceAmount {
         true
     }
 
+    /// Adds `amount`, saturating rather than overflowing.
+    pub fn add(&mut self, amount: &ResourceAmount) {
+        for i in 0..self.res.len() {
+            self.res[i] = self.res[i].saturating_add(amount.res[i]);
+        }
+    }
+
     pub fn get(&self, res: Resource) -> u32 {
         self.res[res as usize]
     }
@@ -75,18 +88,34 @@ impl ResourceAmount {
         enum_iterator::all::<Resource>().all(|res| query.get(res) <= self.get(res))
     }
 
-    pub fn iter(&self) -> std::slice::Iter<u32> {
+    pub fn iter(&self) -> std::slice::Iter<'_, u32> {
         self.res.iter()
     }
 }
 
 impl fmt::Display for ResourceAmount {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in 0..self.res.len() {
-            if let Ok(name) = <_ as TryInto<Resource>>::try_into(i) {
-                write!(f, "{}: {}\t", name, self.res[i])?;
-            }
+        for res in enum_iterator::all::<Resource>() {
+            write!(f, "{}: {}\t", res, self.get(res))?;
         }
         Ok(())
     }
 }
+
+/// Parses a list of the form `Iron=5,Stone=2`. Resources not mentioned are zero.
+impl FromStr for ResourceAmount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut amount = ResourceAmount::new();
+        for entry in s.split(',').filter(|e| !e.is_empty()) {
+            let (name, count) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected RESOURCE=AMOUNT, got {}", entry))?;
+            *amount.get_mut(name.parse()?) = count
+                .parse()
+                .map_err(|_| format!("invalid amount {}", count))?;
+        }
+        Ok(amount)
+    }
+}
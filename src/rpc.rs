@@ -0,0 +1,330 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::api::{LocalPlayerApi, PlayerApi};
+use crate::bank::LoanId;
+use crate::building::{BuildingKind, ProductionId};
+use crate::buy::BuyItem;
+use crate::deal::{Deal, DealId};
+use crate::game_state::{ActionError, ActionOutcome, GameState};
+use crate::market::{OrderId, OrderRequest};
+use crate::player::PlayerId;
+use crate::production::ProductionItem;
+use crate::resource::Resource;
+use crate::rules::Rule;
+use crate::sell::SellItem;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const ACTION_REJECTED: i64 = 1;
+const GAME_FULL: i64 = 2;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: String) -> Self {
+        RpcError {
+            code,
+            message,
+            data: None,
+        }
+    }
+}
+
+impl From<ActionError> for RpcError {
+    fn from(e: ActionError) -> Self {
+        RpcError {
+            code: ACTION_REJECTED,
+            message: e.to_string(),
+            data: serde_json::to_value(e).ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResourceParams {
+    resource: Resource,
+}
+
+#[derive(Deserialize)]
+struct ProduceParams {
+    item: ProductionItem,
+}
+
+#[derive(Deserialize)]
+struct SellParams {
+    item: SellItem,
+}
+
+#[derive(Deserialize)]
+struct BuyParams {
+    item: BuyItem,
+}
+
+#[derive(Deserialize)]
+struct BuildingParams {
+    kind: BuildingKind,
+}
+
+#[derive(Deserialize)]
+struct ProductionParams {
+    id: ProductionId,
+}
+
+#[derive(Deserialize)]
+struct MoveProductionParams {
+    id: ProductionId,
+    position: usize,
+}
+
+#[derive(Deserialize)]
+struct RepeatProductionParams {
+    id: ProductionId,
+    repeat: bool,
+}
+
+#[derive(Deserialize)]
+struct RuleParams {
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct ProposeDealParams {
+    to: PlayerId,
+    deal: Deal,
+}
+
+#[derive(Deserialize)]
+struct DealParams {
+    id: DealId,
+}
+
+#[derive(Deserialize)]
+struct CounterDealParams {
+    id: DealId,
+    deal: Deal,
+}
+
+#[derive(Deserialize)]
+struct TakeLoanParams {
+    amount: u64,
+    periods: u32,
+}
+
+#[derive(Deserialize)]
+struct RepayLoanParams {
+    id: LoanId,
+    amount: u64,
+}
+
+#[derive(Deserialize)]
+struct AmountParams {
+    amount: u64,
+}
+
+#[derive(Deserialize)]
+struct CancelOrderParams {
+    id: OrderId,
+}
+
+fn params<P: DeserializeOwned>(params: Value) -> Result<P, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn result<R: Serialize>(r: R) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(r).unwrap())
+}
+
+fn action(r: Result<ActionOutcome, ActionError>) -> Result<Value, RpcError> {
+    result(r?)
+}
+
+fn dispatch<A: PlayerApi>(api: &mut A, method: &str, p: Value) -> Result<Value, RpcError> {
+    match method {
+        "get_player_id" => result(api.get_player_id()),
+        "get_player" => result(api.get_player()),
+        "get_trades" => result(api.get_trades()),
+        "get_money" => result(api.get_money()),
+        "get_stockpile" => result(api.get_stockpile()),
+        "get_workers" => result(api.get_workers()),
+        "get_idle_workers" => result(api.get_idle_workers()),
+        "get_production_queue" => result(api.get_production_queue()),
+        "get_buildings" => result(api.get_buildings()),
+        "get_offers" => result(api.get_offers()),
+        "get_markets" => result(api.get_markets()),
+        "get_government" => result(api.get_government()),
+        "get_orders" => result(api.get_orders()),
+        "get_rules" => result(api.get_rules()),
+        "get_trade_offers" => result(api.get_trade_offers()),
+        "get_contracts" => result(api.get_contracts()),
+        "get_loans" => result(api.get_loans()),
+        "take_delayed_results" => result(api.take_delayed_results()),
+        "allocate_worker" => action(api.allocate_worker(params::<ResourceParams>(p)?.resource)),
+        "deallocate_worker" => action(api.deallocate_worker(params::<ResourceParams>(p)?.resource)),
+        "produce" => action(api.produce(params::<ProduceParams>(p)?.item)),
+        "cancel_production" => action(api.cancel_production(params::<ProductionParams>(p)?.id)),
+        "move_production" => {
+            let p = params::<MoveProductionParams>(p)?;
+            action(api.move_production(p.id, p.position))
+        }
+        "repeat_production" => {
+            let p = params::<RepeatProductionParams>(p)?;
+            action(api.repeat_production(p.id, p.repeat))
+        }
+        "sell" => action(api.sell(params::<SellParams>(p)?.item)),
+        "buy" => action(api.buy(params::<BuyParams>(p)?.item)),
+        "place_order" => action(api.place_order(params::<OrderRequest>(p)?)),
+        "cancel_order" => action(api.cancel_order(params::<CancelOrderParams>(p)?.id)),
+        "explore" => action(api.explore(params::<ResourceParams>(p)?.resource)),
+        "build" => action(api.build(params::<BuildingParams>(p)?.kind)),
+        "staff_building" => action(api.staff_building(params::<BuildingParams>(p)?.kind)),
+        "unstaff_building" => action(api.unstaff_building(params::<BuildingParams>(p)?.kind)),
+        "add_rule" => action(api.add_rule(params::<Rule>(p)?)),
+        "remove_rule" => action(api.remove_rule(params::<RuleParams>(p)?.index)),
+        "toggle_rule" => action(api.toggle_rule(params::<RuleParams>(p)?.index)),
+        "propose_deal" => {
+            let p = params::<ProposeDealParams>(p)?;
+            action(api.propose_deal(p.to, p.deal))
+        }
+        "accept_deal" => action(api.accept_deal(params::<DealParams>(p)?.id)),
+        "decline_deal" => action(api.decline_deal(params::<DealParams>(p)?.id)),
+        "counter_deal" => {
+            let p = params::<CounterDealParams>(p)?;
+            action(api.counter_deal(p.id, p.deal))
+        }
+        "take_loan" => {
+            let p = params::<TakeLoanParams>(p)?;
+            action(api.take_loan(p.amount, p.periods))
+        }
+        "repay_loan" => {
+            let p = params::<RepayLoanParams>(p)?;
+            action(api.repay_loan(p.id, p.amount))
+        }
+        "deposit" => action(api.deposit(params::<AmountParams>(p)?.amount)),
+        "withdraw" => action(api.withdraw(params::<AmountParams>(p)?.amount)),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {}", method),
+        )),
+    }
+}
+
+fn handle_request(state: &Mutex<GameState>, player: PlayerId, line: &str) -> Option<Response> {
+    let (id, outcome) = match serde_json::from_str::<Request>(line) {
+        Ok(req) if req.jsonrpc != "2.0" => (
+            req.id,
+            Err(RpcError::new(
+                INVALID_REQUEST,
+                "expected jsonrpc 2.0".to_owned(),
+            )),
+        ),
+        Ok(req) => {
+            let mut state = state.lock().unwrap();
+            let mut api = LocalPlayerApi::new(&mut state, player);
+            let outcome = dispatch(&mut api, &req.method, req.params);
+            // Requests without an id are notifications and get no response.
+            (Some(req.id?), outcome)
+        }
+        Err(e) => (None, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+    };
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    Some(Response {
+        jsonrpc: "2.0",
+        id: id.unwrap_or(Value::Null),
+        result,
+        error,
+    })
+}
+
+fn handle_client(state: Arc<Mutex<GameState>>, stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let player = {
+        let mut state = state.lock().unwrap();
+        (!state.is_full()).then(|| state.take_seat())
+    };
+    let Some(player) = player else {
+        let response = Response {
+            jsonrpc: "2.0",
+            id: Value::Null,
+            result: None,
+            error: Some(RpcError::new(GAME_FULL, "the game is full".to_owned())),
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        return writer.write_all(b"\n");
+    };
+    let result = serve_client(&state, player, stream, &mut writer);
+    state.lock().unwrap().leave_seat(player);
+    result
+}
+
+/// Answers the requests of a connected client until it disconnects.
+fn serve_client(
+    state: &Mutex<GameState>,
+    player: PlayerId,
+    stream: TcpStream,
+    writer: &mut TcpStream,
+) -> io::Result<()> {
+    for line in BufReader::new(stream).lines() {
+        if let Some(response) = handle_request(state, player, &line?) {
+            serde_json::to_writer(&mut *writer, &response)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Serves the `PlayerApi` as newline-delimited JSON-RPC 2.0. Every connection takes the seat of
+/// a disconnected client, or is registered as a new player if there is none.
+pub fn run_rpc_server<A: ToSocketAddrs>(state: Arc<Mutex<GameState>>, addr: A) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("accept error: {}", e);
+                continue;
+            }
+        };
+        let state = state.clone();
+        thread::spawn(move || {
+            let _ = handle_client(state, stream);
+        });
+    }
+    Ok(())
+}
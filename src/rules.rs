@@ -0,0 +1,137 @@
+use std::{cmp::Ordering, fmt};
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::buy::BuyItem;
+use crate::game_state::GameAction;
+use crate::player::{Player, WorkerAction};
+use crate::production::ProductionItem;
+use crate::resource::Resource;
+use crate::sell::SellItem;
+
+/// Something about a player that a rule can look at.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Sequence, Serialize)]
+pub enum Quantity {
+    Stock(Resource),
+    Money,
+    /// The number of workers gathering the resource.
+    Gathering(Resource),
+    IdleWorkers,
+}
+
+impl Quantity {
+    fn get(&self, player: &Player) -> u64 {
+        match *self {
+            Quantity::Stock(resource) => player.get_stockpile().get(resource) as u64,
+            Quantity::Money => player.get_money(),
+            Quantity::Gathering(resource) => count_workers(player, WorkerAction::Gather(resource)),
+            Quantity::IdleWorkers => count_workers(player, WorkerAction::Idle),
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Quantity::Stock(resource) => write!(f, "{}", resource),
+            Quantity::Money => write!(f, "money"),
+            Quantity::Gathering(resource) => write!(f, "workers on {}", resource),
+            Quantity::IdleWorkers => write!(f, "idle workers"),
+        }
+    }
+}
+
+fn count_workers(player: &Player, action: WorkerAction) -> u64 {
+    player
+        .workers
+        .iter()
+        .filter(|w| w.current_action == action)
+        .count() as u64
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Condition {
+    Always,
+    AtLeast(Quantity, u64),
+    AtMost(Quantity, u64),
+}
+
+impl Condition {
+    fn holds(&self, player: &Player) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::AtLeast(quantity, value) => quantity.get(player) >= *value,
+            Condition::AtMost(quantity, value) => quantity.get(player) <= *value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Always => write!(f, "always"),
+            Condition::AtLeast(quantity, value) => write!(f, "when {} >= {}", quantity, value),
+            Condition::AtMost(quantity, value) => write!(f, "when {} <= {}", quantity, value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum RuleAction {
+    Sell(SellItem),
+    Buy(BuyItem),
+    Produce(ProductionItem),
+    /// Moves workers onto or off the resource, one per tick, until it has the given number.
+    KeepWorkers(Resource, u32),
+}
+
+impl fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleAction::Sell(item) => write!(f, "sell {}", item),
+            RuleAction::Buy(item) => write!(f, "buy {}", item),
+            RuleAction::Produce(item) => write!(f, "produce {}", item),
+            RuleAction::KeepWorkers(resource, count) => {
+                write!(f, "keep {} workers on {}", count, resource)
+            }
+        }
+    }
+}
+
+/// A rule that takes an action on behalf of its player every tick its condition holds.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Rule {
+    pub condition: Condition,
+    pub action: RuleAction,
+    pub enabled: bool,
+}
+
+impl Rule {
+    /// The action the rule takes for `player` in its current state, if any.
+    pub fn fire(&self, player: &Player) -> Option<GameAction> {
+        if !self.enabled || !self.condition.holds(player) {
+            return None;
+        }
+        let id = player.get_id();
+        match self.action {
+            RuleAction::Sell(item) => Some(GameAction::Sell(id, item)),
+            RuleAction::Buy(item) => Some(GameAction::Buy(id, item)),
+            RuleAction::Produce(item) => Some(GameAction::Produce(id, item)),
+            RuleAction::KeepWorkers(resource, count) => {
+                let gathering = Quantity::Gathering(resource).get(player);
+                match gathering.cmp(&(count as u64)) {
+                    Ordering::Less => Some(GameAction::AllocateWorker(id, resource)),
+                    Ordering::Greater => Some(GameAction::DeallocateWorker(id, resource)),
+                    Ordering::Equal => None,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.condition, self.action)
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt, fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::content;
+use crate::game_state::GameState;
+
+/// Bumped whenever the serialized layout of `GameState` changes.
+pub const SAVE_VERSION: u16 = 17;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    UnsupportedVersion(u16),
+    ContentMismatch,
+    Malformed(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::UnsupportedVersion(v) => write!(
+                f,
+                "save file version {} is not supported, expected {}",
+                v, SAVE_VERSION
+            ),
+            SaveError::ContentMismatch => write!(
+                f,
+                "the game was saved with other content; load it with the same --content file"
+            ),
+            SaveError::Malformed(e) => write!(f, "malformed save file: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+/// `content` is the fingerprint of the content the game was played with.
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+    version: u16,
+    content: u64,
+    state: &'a GameState,
+}
+
+/// The state is only deserialized once the version and content are known to match, so that an
+/// old save is reported as such rather than as malformed.
+#[derive(Deserialize)]
+struct SaveFile {
+    version: u16,
+    content: u64,
+    state: Value,
+}
+
+/// Writes `state` in the save format, on a single line.
+pub fn write_save<W: Write>(state: &GameState, writer: W) -> Result<(), SaveError> {
+    let file = SaveFileRef {
+        version: SAVE_VERSION,
+        content: content::get().fingerprint(),
+        state,
+    };
+    serde_json::to_writer(writer, &file).map_err(|e| SaveError::Io(e.into()))
+}
+
+pub fn read_save(text: &str) -> Result<GameState, SaveError> {
+    let file: SaveFile =
+        serde_json::from_str(text).map_err(|e| SaveError::Malformed(e.to_string()))?;
+    if file.version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(file.version));
+    }
+    if file.content != content::get().fingerprint() {
+        return Err(SaveError::ContentMismatch);
+    }
+    serde_json::from_value(file.state).map_err(|e| SaveError::Malformed(e.to_string()))
+}
+
+/// Writes `state` to `path` as JSON. The file is written next to `path` first and then moved
+/// into place, so an interrupted save leaves any previous save intact.
+pub fn save_game<P: AsRef<Path>>(state: &GameState, path: P) -> Result<(), SaveError> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    {
+        let mut writer = BufWriter::new(fs::File::create(&tmp)?);
+        write_save(state, &mut writer)?;
+        writer.flush()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+pub fn load_game<P: AsRef<Path>>(path: P) -> Result<GameState, SaveError> {
+    read_save(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(state: &GameState) -> Value {
+        let mut text = Vec::new();
+        write_save(state, &mut text).unwrap();
+        serde_json::from_slice(&text).unwrap()
+    }
+
+    #[test]
+    fn saves_load_back() {
+        let mut state = GameState::new(3);
+        state.register_player();
+        for _ in 0..50 {
+            state.step();
+        }
+        let file = saved(&state);
+        let loaded = read_save(&file.to_string()).unwrap();
+        assert_eq!(loaded.get_tick(), 50);
+        assert_eq!(saved(&loaded), file);
+    }
+
+    #[test]
+    fn saves_from_other_versions_or_content_are_refused() {
+        let mut file = saved(&GameState::new(3));
+        file["version"] = (SAVE_VERSION - 1).into();
+        assert!(matches!(
+            read_save(&file.to_string()),
+            Err(SaveError::UnsupportedVersion(_))
+        ));
+        let mut file = saved(&GameState::new(3));
+        file["content"] = (content::get().fingerprint() ^ 1).into();
+        assert!(matches!(
+            read_save(&file.to_string()),
+            Err(SaveError::ContentMismatch)
+        ));
+    }
+}
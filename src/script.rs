@@ -0,0 +1,659 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use enum_iterator::Sequence;
+use rhai::packages::{Package, StandardPackage};
+use rhai::{Dynamic, Engine, EvalAltResult, Module, ParseError, Shared, AST, INT};
+
+use crate::api::PlayerApi;
+use crate::building::BuildingKind;
+use crate::buy::BuyItem;
+use crate::game_state::GameAction;
+use crate::player::PlayerId;
+use crate::production::ProductionItem;
+use crate::protocol::{MarketSnapshot, PlayerSnapshot, ResourceQuantity};
+use crate::resource::Resource;
+use crate::sell::SellItem;
+use crate::strategy::Strategy;
+
+/// How many operations a script may take in a tick, as counted by the engine.
+pub const STEP_BUDGET: u64 = 50_000;
+/// The most lines of output a bot keeps.
+const LOG_LINES: usize = 200;
+/// The longest string a script may build, and the longest line it may log.
+const MAX_STRING_LEN: usize = 1000;
+/// How deeply blocks and expressions may nest.
+const MAX_NESTING: usize = 64;
+/// How deeply functions defined in the script may call each other.
+const MAX_CALL_LEVELS: usize = 16;
+/// The most elements an array or object map may hold.
+const MAX_COLLECTION_LEN: usize = 1000;
+/// The most values a script may keep with `remember`, and the most bytes their names and values may
+/// take up together.
+const MAX_MEMORY_ENTRIES: usize = 1000;
+const MAX_MEMORY_BYTES: usize = 64 * 1024;
+
+type RhaiResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    /// The line the error was raised on, or 0 if the engine does not know it, as for arithmetic
+    /// errors.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ScriptError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        ScriptError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.message),
+            line => write!(f, "line {}: {}", line, self.message),
+        }
+    }
+}
+
+impl From<ParseError> for ScriptError {
+    fn from(e: ParseError) -> Self {
+        ScriptError::new(e.1.line().unwrap_or(0), e.0.to_string())
+    }
+}
+
+impl From<Box<EvalAltResult>> for ScriptError {
+    /// Reports an error raised inside a function where it was raised, or where the function was
+    /// called if the function is not the script's own.
+    fn from(mut e: Box<EvalAltResult>) -> Self {
+        let mut line = e.position().line();
+        while let EvalAltResult::ErrorInFunctionCall(_, _, inner, _) = *e {
+            e = inner;
+            line = e.position().line().or(line);
+        }
+        e.clear_position();
+        let line = line.unwrap_or(0);
+        match *e {
+            EvalAltResult::ErrorRuntime(message, _) => ScriptError::new(line, message.to_string()),
+            e => ScriptError::new(line, e.to_string()),
+        }
+    }
+}
+
+/// What a script keeps with `remember` from one run to the next.
+#[derive(Debug, Default)]
+pub struct Memory {
+    values: HashMap<String, Dynamic>,
+    bytes: usize,
+}
+
+fn memory_size(key: &str, value: &Dynamic) -> usize {
+    key.len()
+        + value
+            .read_lock::<rhai::ImmutableString>()
+            .map_or(std::mem::size_of::<INT>(), |s| s.len())
+}
+
+impl Memory {
+    fn get(&self, key: &str) -> Option<&Dynamic> {
+        self.values.get(key)
+    }
+
+    /// Keeps `value` under `key`, or returns false and keeps nothing if that would take the
+    /// memory over its limits.
+    fn set(&mut self, key: &str, value: Dynamic) -> bool {
+        let old = self.values.get(key).map_or(0, |old| memory_size(key, old));
+        let bytes = self.bytes - old + memory_size(key, &value);
+        let entries = self.values.len() + usize::from(!self.values.contains_key(key));
+        if bytes > MAX_MEMORY_BYTES || entries > MAX_MEMORY_ENTRIES {
+            return false;
+        }
+        self.bytes = bytes;
+        self.values.insert(key.to_owned(), value);
+        true
+    }
+}
+
+/// Applies the limits every script is held to, when it is compiled as well as when it runs.
+fn limit(engine: &mut Engine) {
+    engine
+        .set_max_expr_depths(MAX_NESTING, MAX_NESTING)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_LEN)
+        .set_max_array_size(MAX_COLLECTION_LEN)
+        .set_max_map_size(MAX_COLLECTION_LEN);
+}
+
+/// The engine's standard functions, built once and shared by every run.
+fn standard_package() -> Shared<Module> {
+    static PACKAGE: OnceLock<Shared<Module>> = OnceLock::new();
+    PACKAGE
+        .get_or_init(|| StandardPackage::new().as_shared_module())
+        .clone()
+}
+
+/// A compiled bot script.
+#[derive(Debug)]
+pub struct Program {
+    ast: AST,
+}
+
+impl Program {
+    pub fn parse(source: &str) -> Result<Self, ScriptError> {
+        let mut engine = Engine::new_raw();
+        limit(&mut engine);
+        Ok(Program {
+            ast: engine.compile(source)?,
+        })
+    }
+
+    /// Runs the script once against `player` and `markets`, within `budget` operations. Returns
+    /// the actions it took and what it printed, in order. `memory` holds what the script keeps
+    /// with `remember` from one run to the next.
+    pub fn run(
+        &self,
+        tick: u64,
+        player: PlayerSnapshot,
+        markets: Vec<MarketSnapshot>,
+        memory: &mut Memory,
+        budget: u64,
+    ) -> Result<(Vec<GameAction>, Vec<String>), ScriptError> {
+        let run = Arc::new(Mutex::new(Run {
+            tick,
+            player,
+            markets,
+            memory: std::mem::take(memory),
+            actions: Vec::new(),
+            log: Vec::new(),
+        }));
+        let mut engine = Engine::new_raw();
+        limit(&mut engine);
+        // The engine takes a budget of zero as no limit at all.
+        engine.set_max_operations(budget.max(1));
+        engine.register_global_module(standard_package());
+        register(&mut engine, &run);
+        let result = engine.run_ast(&self.ast);
+        drop(engine);
+
+        let run = Arc::try_unwrap(run)
+            .ok()
+            .expect("the engine holds the run until dropped")
+            .into_inner()
+            .unwrap();
+        *memory = run.memory;
+        result?;
+        Ok((run.actions, run.log))
+    }
+}
+
+/// What a run of a script sees and what it has done so far.
+struct Run {
+    tick: u64,
+    player: PlayerSnapshot,
+    markets: Vec<MarketSnapshot>,
+    memory: Memory,
+    actions: Vec<GameAction>,
+    log: Vec<String>,
+}
+
+impl Run {
+    fn market(&self, resource: Resource) -> Option<&MarketSnapshot> {
+        self.markets.iter().find(|m| m.resource == resource)
+    }
+}
+
+/// Numbers the script cannot hold are given to it as the largest one it can.
+fn int(n: u64) -> INT {
+    INT::try_from(n).unwrap_or(INT::MAX)
+}
+
+fn price(price: Option<u64>) -> Dynamic {
+    price.map_or(Dynamic::UNIT, |p| Dynamic::from_int(int(p)))
+}
+
+fn amount(quantities: &[ResourceQuantity], resource: Resource) -> Dynamic {
+    let amount = quantities
+        .iter()
+        .find(|q| q.resource == resource)
+        .map_or(0, |q| q.amount);
+    Dynamic::from_int(amount.into())
+}
+
+fn lookup<T: Sequence + fmt::Display>(function: &str, name: &str) -> RhaiResult<T> {
+    enum_iterator::all::<T>()
+        .find(|item| item.to_string() == name)
+        .ok_or_else(|| format!("{}: unknown {}", function, name).into())
+}
+
+/// Cuts `line` down to at most `MAX_STRING_LEN` bytes.
+fn log_line(line: &str) -> String {
+    let mut end = line.len().min(MAX_STRING_LEN);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    line[..end].to_owned()
+}
+
+fn query(engine: &mut Engine, run: &Arc<Mutex<Run>>, name: &str, f: fn(&Run) -> u64) {
+    let run = run.clone();
+    engine.register_fn(name, move || int(f(&run.lock().unwrap())));
+}
+
+/// Registers a function taking the name of a `T` and answering from the run.
+fn named_query<T: Sequence + fmt::Display + 'static>(
+    engine: &mut Engine,
+    run: &Arc<Mutex<Run>>,
+    name: &'static str,
+    f: fn(&Run, T) -> Dynamic,
+) {
+    let run = run.clone();
+    engine.register_fn(name, move |arg: &str| -> RhaiResult<Dynamic> {
+        let item = lookup(name, arg)?;
+        Ok(f(&run.lock().unwrap(), item))
+    });
+}
+
+/// Registers a function taking the name of a `T` and taking the action `f` makes of it.
+fn action<T: Sequence + fmt::Display + 'static>(
+    engine: &mut Engine,
+    run: &Arc<Mutex<Run>>,
+    name: &'static str,
+    f: fn(PlayerId, T) -> GameAction,
+) {
+    let run = run.clone();
+    engine.register_fn(name, move |arg: &str| -> RhaiResult<()> {
+        let item = lookup(name, arg)?;
+        let mut run = run.lock().unwrap();
+        let action = f(run.player.id, item);
+        run.actions.push(action);
+        Ok(())
+    });
+}
+
+/// Gives the engine everything a script can call: queries of its player and the markets, the
+/// actions it can take, and `recall` and `remember` for its memory. `print` and `debug` write to
+/// its log.
+fn register(engine: &mut Engine, run: &Arc<Mutex<Run>>) {
+    {
+        let run = run.clone();
+        engine.on_print(move |s| run.lock().unwrap().log.push(log_line(s)));
+    }
+    {
+        let run = run.clone();
+        engine.on_debug(move |s, _, _| run.lock().unwrap().log.push(log_line(s)));
+    }
+    {
+        let run = run.clone();
+        engine.register_fn("recall", move |key: &str, default: Dynamic| {
+            let run = run.lock().unwrap();
+            run.memory.get(key).cloned().unwrap_or(default)
+        });
+    }
+    {
+        let run = run.clone();
+        engine.register_fn(
+            "remember",
+            move |key: &str, value: Dynamic| -> RhaiResult<()> {
+                if !(value.is_unit() || value.is_bool() || value.is_int() || value.is_string()) {
+                    return Err("remember keeps only numbers, strings, true, false and ()".into());
+                }
+                if !run.lock().unwrap().memory.set(key, value) {
+                    return Err("out of memory for remember".into());
+                }
+                Ok(())
+            },
+        );
+    }
+
+    query(engine, run, "tick", |run| run.tick);
+    query(engine, run, "money", |run| run.player.money);
+    query(engine, run, "wages_due", |run| run.player.wages_due);
+    query(engine, run, "idle", |run| run.player.idle_workers.into());
+    query(engine, run, "queued", |run| {
+        run.player.production_queue.len() as u64
+    });
+    named_query(engine, run, "stock", |run, res: Resource| {
+        amount(&run.player.stockpile, res)
+    });
+    named_query(engine, run, "capacity", |run, res: Resource| {
+        amount(&run.player.capacity, res)
+    });
+    named_query(engine, run, "workers", |run, res: Resource| {
+        amount(&run.player.gathering_workers, res)
+    });
+    named_query(engine, run, "buildings", |run, kind: BuildingKind| {
+        let count = run
+            .player
+            .buildings
+            .iter()
+            .filter(|b| b.kind == kind && b.construction_ticks == 0)
+            .count();
+        Dynamic::from_int(int(count as u64))
+    });
+    named_query(engine, run, "bid", |run, res: Resource| {
+        price(
+            run.market(res)
+                .and_then(|m| m.bids.first())
+                .map(|l| l.price),
+        )
+    });
+    named_query(engine, run, "ask", |run, res: Resource| {
+        price(
+            run.market(res)
+                .and_then(|m| m.asks.first())
+                .map(|l| l.price),
+        )
+    });
+    named_query(engine, run, "last_price", |run, res: Resource| {
+        price(run.market(res).and_then(|m| m.last_price))
+    });
+
+    action::<SellItem>(engine, run, "sell", GameAction::Sell);
+    action::<BuyItem>(engine, run, "buy", GameAction::Buy);
+    action::<ProductionItem>(engine, run, "produce", GameAction::Produce);
+    action(engine, run, "allocate", GameAction::AllocateWorker);
+    action(engine, run, "deallocate", GameAction::DeallocateWorker);
+    action(engine, run, "explore", GameAction::Explore);
+    action(engine, run, "build", GameAction::Build);
+    action(engine, run, "staff", GameAction::StaffBuilding);
+    action(engine, run, "unstaff", GameAction::UnstaffBuilding);
+}
+
+/// A bot run by a script. Every tick the script runs from the top against a snapshot of the
+/// player and the markets; the actions it took are then submitted in order. A script that fails
+/// or runs out of operations takes no actions that tick.
+pub struct ScriptBot {
+    program: Arc<Program>,
+    memory: Memory,
+    log: VecDeque<String>,
+}
+
+impl ScriptBot {
+    pub fn new(program: Arc<Program>) -> Self {
+        ScriptBot {
+            program,
+            memory: Memory::default(),
+            log: VecDeque::new(),
+        }
+    }
+
+    /// What the script logged and the errors it ran into, oldest first.
+    pub fn get_log(&self) -> impl Iterator<Item = &String> {
+        self.log.iter()
+    }
+
+    fn push_log(&mut self, line: String) {
+        if self.log.len() == LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+}
+
+impl Strategy for ScriptBot {
+    fn act(&mut self, tick: u64, api: &mut dyn PlayerApi) {
+        let player = api.get_player();
+        let markets = api.get_markets();
+        let run = self
+            .program
+            .run(tick, player, markets, &mut self.memory, STEP_BUDGET);
+        match run {
+            Ok((actions, lines)) => {
+                for line in lines {
+                    self.push_log(format!("{}: {}", tick, line));
+                }
+                for action in actions {
+                    if let Err(e) = api.submit(action) {
+                        self.push_log(format!("{}: {:?} failed: {}", tick, action, e));
+                    }
+                }
+            }
+            Err(e) => self.push_log(format!("{}: error at {}", tick, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::GameState;
+
+    fn snapshots() -> (PlayerSnapshot, Vec<MarketSnapshot>) {
+        let mut state = GameState::new(0);
+        let id = state.register_player();
+        state
+            .handle_action(GameAction::AllocateWorker(id, Resource::Iron))
+            .unwrap();
+        (
+            PlayerSnapshot::new(state.get_player(id), &state),
+            MarketSnapshot::current(&state),
+        )
+    }
+
+    fn run_with(
+        source: &str,
+        memory: &mut Memory,
+        budget: u64,
+    ) -> Result<(Vec<GameAction>, Vec<String>), ScriptError> {
+        let (player, markets) = snapshots();
+        Program::parse(source)?.run(7, player, markets, memory, budget)
+    }
+
+    fn run(source: &str) -> Result<(Vec<GameAction>, Vec<String>), ScriptError> {
+        run_with(source, &mut Memory::default(), STEP_BUDGET)
+    }
+
+    fn error(source: &str) -> ScriptError {
+        run(source).unwrap_err()
+    }
+
+    /// What the script prints, one string per `print` call.
+    fn logged(source: &str) -> Vec<String> {
+        run(source).unwrap().1
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+        let e = Program::parse("print(1);\n\nlet = 3;").unwrap_err();
+        assert_eq!(e.line, 3);
+        assert_eq!(
+            error("print(1);\n\nprint(y);"),
+            ScriptError::new(3, "Variable not found: y")
+        );
+        assert_eq!(
+            error("nope()"),
+            ScriptError::new(1, "Function not found: nope ()")
+        );
+        // An error in a function of the script is reported where it was raised.
+        assert_eq!(
+            error("fn f() {\n stock(\"Gold\")\n}\nf()"),
+            ScriptError::new(2, "stock: unknown Gold")
+        );
+    }
+
+    #[test]
+    fn arithmetic_is_checked() {
+        let e = error(&format!("print({} + 1)", INT::MAX));
+        assert!(e.message.starts_with("Addition overflow"), "{}", e);
+        let e = error("let x = 0; print(1 / x)");
+        assert!(e.message.starts_with("Division by zero"), "{}", e);
+        assert_eq!(e.to_string(), e.message);
+    }
+
+    #[test]
+    fn large_numbers_saturate() {
+        let (mut player, markets) = snapshots();
+        player.money = u64::MAX;
+        player.wages_due = INT::MAX as u64 + 1;
+        let program = Program::parse("print(money()); print(wages_due())").unwrap();
+        let (_, log) = program
+            .run(7, player, markets, &mut Memory::default(), STEP_BUDGET)
+            .unwrap();
+        assert_eq!(log, [INT::MAX.to_string(), INT::MAX.to_string()]);
+    }
+
+    #[test]
+    fn strings_and_log_lines_are_limited_in_length() {
+        let e = error("let s = \"xxxxxxxxxx\";\nloop { s += s; }");
+        assert_eq!(e, ScriptError::new(2, "Length of string too large"));
+        // Printing a value whose text would be too long fails the same way.
+        let e = error("let a = []; for i in 0..500 { a.push(i); }\nprint(a);");
+        assert_eq!(e, ScriptError::new(2, "Length of string too large"));
+        assert_eq!(log_line(&"é".repeat(MAX_STRING_LEN)).len(), MAX_STRING_LEN);
+    }
+
+    #[test]
+    fn running_out_of_operations_stops_the_script() {
+        let e = error("let n = 0;\nloop {\n n += 1;\n}");
+        assert_eq!(e.message, "Too many operations");
+        // A budget that suffices runs to the end.
+        assert_eq!(
+            logged("let n = 0; while n < 100 { n += 1; } print(n);"),
+            ["100"]
+        );
+        let e = run_with("print(1); print(2);", &mut Memory::default(), 1).unwrap_err();
+        assert_eq!(e.message, "Too many operations");
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let chain = vec!["1"; 20_000].join(" + ");
+        assert!(Program::parse(&format!("print({})", chain)).is_err());
+        let parens = format!("print({}1{})", "(".repeat(100), ")".repeat(100));
+        assert!(Program::parse(&parens).is_err());
+        let blocks = format!("{}{}", "if true { ".repeat(100), "}".repeat(100));
+        assert!(Program::parse(&blocks).is_err());
+        let recursion = "fn f(n) { f(n + 1) } f(0)";
+        assert!(error(recursion).message.contains("Stack overflow"));
+    }
+
+    #[test]
+    fn memory_persists_between_runs() {
+        let source = "let n = recall(\"runs\", 0) + 1; remember(\"runs\", n); print(n);";
+        let mut memory = Memory::default();
+        for expected in ["1", "2", "3"] {
+            assert_eq!(
+                run_with(source, &mut memory, STEP_BUDGET).unwrap().1,
+                [expected]
+            );
+        }
+        assert_eq!(memory.get("runs").unwrap().as_int(), Ok(3));
+        // Variables do not.
+        assert_eq!(error("print(n)").message, "Variable not found: n");
+        assert_eq!(
+            error("remember(\"a\", [1, 2])").message,
+            "remember keeps only numbers, strings, true, false and ()"
+        );
+    }
+
+    #[test]
+    fn memory_is_limited() {
+        let mut memory = Memory::default();
+        let many = "let i = 0;\nloop {\n remember(\"k\" + i, i);\n i += 1;\n}";
+        let e = run_with(many, &mut memory, u64::MAX).unwrap_err();
+        assert_eq!(e, ScriptError::new(3, "out of memory for remember"));
+        assert_eq!(memory.values.len(), MAX_MEMORY_ENTRIES);
+
+        let mut memory = Memory::default();
+        let large = concat!(
+            "let s = \"x\"; s.pad(800, \"x\");\n",
+            "let i = 0;\nloop { remember(\"k\" + i, s); i += 1; }"
+        );
+        let e = run_with(large, &mut memory, u64::MAX).unwrap_err();
+        assert_eq!(e.message, "out of memory for remember");
+        assert!(memory.bytes <= MAX_MEMORY_BYTES);
+        assert!(memory.values.len() < MAX_MEMORY_ENTRIES);
+        // Overwriting a value does not count it twice.
+        let mut memory = Memory::default();
+        let same = "let i = 0; while i < 5000 { remember(\"k\", i); i += 1; }";
+        run_with(same, &mut memory, u64::MAX).unwrap();
+        assert_eq!(memory.values.len(), 1);
+    }
+
+    #[test]
+    fn queries_read_the_snapshots() {
+        let (player, markets) = snapshots();
+        let stone = markets
+            .iter()
+            .find(|m| m.resource == Resource::Stone)
+            .unwrap();
+        let iron = markets
+            .iter()
+            .find(|m| m.resource == Resource::Iron)
+            .unwrap();
+        let price = |p: Option<u64>| p.map_or("".to_owned(), |p| p.to_string());
+        let cases = [
+            ("tick()", "7".to_owned()),
+            ("money()", player.money.to_string()),
+            ("wages_due()", player.wages_due.to_string()),
+            ("idle()", player.idle_workers.to_string()),
+            ("queued()", "0".to_owned()),
+            ("stock(\"Iron\")", "0".to_owned()),
+            ("capacity(\"Iron\")", player.capacity[0].amount.to_string()),
+            ("workers(\"Iron\")", "1".to_owned()),
+            ("workers(\"Stone\")", "0".to_owned()),
+            ("buildings(\"Smelter\")", "1".to_owned()),
+            ("buildings(\"Mine\")", "0".to_owned()),
+            ("bid(\"Stone\")", price(stone.bids.first().map(|l| l.price))),
+            ("ask(\"Iron\")", price(iron.asks.first().map(|l| l.price))),
+            ("last_price(\"Iron\")", price(iron.last_price)),
+        ];
+        for (call, expected) in cases {
+            assert_eq!(logged(&format!("print({})", call)), [expected], "{}", call);
+        }
+        assert_eq!(logged("print(last_price(\"Iron\") == ())"), ["true"]);
+        assert_eq!(error("stock(\"Gold\")").message, "stock: unknown Gold");
+        assert_eq!(error("stock(1)").message, "Function not found: stock (i64)");
+    }
+
+    #[test]
+    fn actions_are_collected_in_order() {
+        let source = concat!(
+            "sell(\"Iron\"); buy(\"Stone\"); produce(\"IronPlate\");\n",
+            "allocate(\"Copper\"); deallocate(\"Iron\"); explore(\"Stone\");\n",
+            "build(\"Mine\"); staff(\"Smelter\"); unstaff(\"Smelter\");"
+        );
+        let (actions, log) = run(source).unwrap();
+        assert!(log.is_empty());
+        let actions: Vec<_> = actions.iter().map(|a| format!("{:?}", a)).collect();
+        assert_eq!(
+            actions,
+            [
+                "Sell(0, Iron)",
+                "Buy(0, Stone)",
+                "Produce(0, IronPlate)",
+                "AllocateWorker(0, Copper)",
+                "DeallocateWorker(0, Iron)",
+                "Explore(0, Stone)",
+                "Build(0, Mine)",
+                "StaffBuilding(0, Smelter)",
+                "UnstaffBuilding(0, Smelter)",
+            ]
+        );
+        assert_eq!(
+            error("sell(\"Workshop\")").message,
+            "sell: unknown Workshop"
+        );
+    }
+
+    #[test]
+    fn a_failed_run_takes_no_actions() {
+        let mut bot = ScriptBot::new(Arc::new(
+            Program::parse("sell(\"Iron\");\nstock(\"Gold\");").unwrap(),
+        ));
+        let mut state = GameState::new(0);
+        let id = state.register_player();
+        let mut api = crate::api::LocalPlayerApi::new(&mut state, id);
+        bot.act(3, &mut api);
+        let log: Vec<_> = bot.get_log().collect();
+        assert_eq!(log, ["3: error at line 2: stock: unknown Gold"]);
+    }
+}
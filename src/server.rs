@@ -0,0 +1,277 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::ai::AiPlayers;
+use crate::clock::{GameClock, GameSpeed};
+use crate::game_state::{ActionError, ActionOutcome, Duration, GameState};
+use crate::player::PlayerId;
+use crate::policy::ActionSource;
+use crate::protocol::{
+    read_message, write_message, ClientMessage, Encoding, GameSnapshot, ProtocolError,
+    ServerMessage,
+};
+
+/// How many messages may wait to be written to a connection before it is dropped as too slow.
+const SEND_QUEUE: usize = 256;
+
+/// Messages to a connection are queued and written by a thread of its own, so that a slow client
+/// never holds up the game.
+struct Connection {
+    player: PlayerId,
+    queue: SyncSender<Arc<ServerMessage>>,
+    stream: TcpStream,
+}
+
+impl Connection {
+    /// Queues `msg`, or shuts the connection down if its queue is full or its writer has stopped.
+    fn send(&self, msg: Arc<ServerMessage>) -> bool {
+        let queued = self.queue.try_send(msg).is_ok();
+        if !queued {
+            let _ = self.stream.shutdown(Shutdown::Both);
+        }
+        queued
+    }
+}
+
+fn write_messages(mut stream: TcpStream, encoding: Encoding, queue: Receiver<Arc<ServerMessage>>) {
+    for msg in queue {
+        if write_message(&mut stream, encoding, &*msg).is_err() {
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
+        }
+    }
+}
+
+type Connections = Arc<Mutex<Vec<Arc<Connection>>>>;
+
+fn broadcast(msg: ServerMessage, connections: &Connections) {
+    let msg = Arc::new(msg);
+    connections
+        .lock()
+        .unwrap()
+        .retain(|conn| conn.send(msg.clone()));
+}
+
+fn run_clock(state: Arc<Mutex<GameState>>, mut ais: AiPlayers, connections: Connections) {
+    let tick = std::time::Duration::from_millis(1000 / Duration::TICKS_PER_SEC);
+    let mut clock = GameClock::new(GameSpeed::Normal);
+    let mut last_snapshot = GameSnapshot::new(&state.lock().unwrap());
+    loop {
+        let started = Instant::now();
+        {
+            let mut state = state.lock().unwrap();
+            clock.advance(state.is_paused(), || {
+                ais.act(&mut state);
+                state.step();
+            });
+            let snapshot = GameSnapshot::new(&state);
+            // Only queued while the state is locked, so that a connection that joins gets every
+            // delta after its snapshot.
+            broadcast(
+                ServerMessage::Delta(snapshot.delta_from(&last_snapshot)),
+                &connections,
+            );
+            for conn in connections.lock().unwrap().iter() {
+                for result in state.take_delayed_results(conn.player) {
+                    conn.send(Arc::new(ServerMessage::DelayedResult(result)));
+                }
+            }
+            last_snapshot = snapshot;
+        }
+        thread::sleep(tick.saturating_sub(started.elapsed()));
+    }
+}
+
+fn handle_client(
+    state: Arc<Mutex<GameState>>,
+    connections: Connections,
+    stream: TcpStream,
+) -> Result<(), ProtocolError> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let encoding = match read_message(&mut reader)? {
+        (encoding, ClientMessage::Hello) => encoding,
+        (encoding, _) => {
+            let mut stream = stream;
+            return write_message(
+                &mut stream,
+                encoding,
+                &ServerMessage::Error {
+                    message: "expected Hello".to_owned(),
+                },
+            );
+        }
+    };
+
+    let writer = stream.try_clone()?;
+    let (queue, queued) = mpsc::sync_channel(SEND_QUEUE);
+    thread::spawn(move || write_messages(writer, encoding, queued));
+
+    let conn = {
+        let mut state = state.lock().unwrap();
+        if state.is_full() {
+            let _ = queue.try_send(Arc::new(ServerMessage::Error {
+                message: "the game is full".to_owned(),
+            }));
+            return Ok(());
+        }
+        let conn = Arc::new(Connection {
+            player: state.take_seat(),
+            queue,
+            stream,
+        });
+        conn.send(Arc::new(ServerMessage::Welcome {
+            player: conn.player,
+        }));
+        conn.send(Arc::new(ServerMessage::Snapshot(GameSnapshot::new(&state))));
+        // Joined while the state is still locked, so that no delta can slip between the snapshot
+        // and the connection.
+        connections.lock().unwrap().push(conn.clone());
+        conn
+    };
+
+    let result = serve_client(&state, &conn, &mut reader);
+    let _ = conn.stream.shutdown(Shutdown::Both);
+    connections
+        .lock()
+        .unwrap()
+        .retain(|c| !Arc::ptr_eq(c, &conn));
+    state.lock().unwrap().leave_seat(conn.player);
+    result
+}
+
+/// Answers the messages of a connected client until it disconnects.
+fn serve_client(
+    state: &Mutex<GameState>,
+    conn: &Connection,
+    reader: &mut BufReader<TcpStream>,
+) -> Result<(), ProtocolError> {
+    loop {
+        let reply = match read_message(reader) {
+            Ok((_, ClientMessage::Hello)) => None,
+            Ok((_, ClientMessage::Action { id, action })) => {
+                let result = if action.get_player() == Some(conn.player) {
+                    state
+                        .lock()
+                        .unwrap()
+                        .submit_action(action, ActionSource::Api)
+                } else {
+                    Err(ActionError::NotPermitted)
+                };
+                Some(match result {
+                    Ok(ActionOutcome::Applied) => ServerMessage::Ack { id },
+                    Ok(ActionOutcome::Delayed { until_tick }) => {
+                        ServerMessage::Delayed { id, until_tick }
+                    }
+                    Err(reason) => ServerMessage::Rejected { id, reason },
+                })
+            }
+            Ok((_, ClientMessage::RequestSnapshot)) => Some(ServerMessage::Snapshot(
+                GameSnapshot::new(&state.lock().unwrap()),
+            )),
+            Err(ProtocolError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e @ ProtocolError::Io(_)) | Err(e @ ProtocolError::FrameTooLarge(_)) => {
+                return Err(e)
+            }
+            Err(e) => Some(ServerMessage::Error {
+                message: e.to_string(),
+            }),
+        };
+        if let Some(reply) = reply {
+            conn.send(Arc::new(reply));
+        }
+    }
+}
+
+/// Shuts the server down once `quit` is typed on its console. A closed console is ignored, so
+/// that a server can run detached from one.
+fn wait_for_quit(state: Arc<Mutex<GameState>>) {
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) if line.trim() == "quit" => {
+                state.lock().unwrap().end_journal();
+                std::process::exit(0);
+            }
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// Serves the game at `addr`, stepping it along with `ais`. Every connection takes over the player
+/// a disconnected client left, or joins as a new player if there is none.
+pub fn run_server<A: ToSocketAddrs>(
+    state: Arc<Mutex<GameState>>,
+    ais: AiPlayers,
+    addr: A,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let connections = Connections::default();
+
+    {
+        let state = state.clone();
+        let connections = connections.clone();
+        thread::spawn(move || run_clock(state, ais, connections));
+    }
+    {
+        let state = state.clone();
+        thread::spawn(move || wait_for_quit(state));
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("accept error: {}", e);
+                continue;
+            }
+        };
+        let state = state.clone();
+        let connections = connections.clone();
+        thread::spawn(move || {
+            let _ = handle_client(state, connections, stream);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::MAX_PLAYERS;
+
+    #[test]
+    fn disconnected_seats_are_taken_over() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(GameState::new(0)));
+        let connections = Connections::default();
+        for _ in 0..MAX_PLAYERS + 10 {
+            let mut client = TcpStream::connect(addr).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            let server = {
+                let state = state.clone();
+                let connections = connections.clone();
+                thread::spawn(move || handle_client(state, connections, stream))
+            };
+            write_message(&mut client, Encoding::Binary, &ClientMessage::Hello).unwrap();
+            let (_, welcome) = read_message(&mut client).unwrap();
+            assert!(matches!(welcome, ServerMessage::Welcome { player: 0 }));
+            // Dropping the client before it reads the snapshot may reset the connection, which
+            // must free the seat all the same.
+            drop(client);
+            let _ = server.join().unwrap();
+        }
+        let state = state.lock().unwrap();
+        assert_eq!(state.players().count(), 1);
+        assert!(!state.is_full());
+        assert!(connections.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,434 @@
+use serde::Deserialize;
+use std::{fmt, fs, str::FromStr, sync::Arc};
+
+use crate::api::PlayerApi;
+use crate::building::BuildingKind;
+use crate::content;
+use crate::game_state::GameAction;
+use crate::npc::NpcItem;
+use crate::production::ProductionItem;
+use crate::protocol::{PlayerSnapshot, ResourceQuantity};
+use crate::resource::Resource;
+use crate::script::{Program, ScriptBot};
+use crate::sell::SellItem;
+
+/// A bot playing through the `PlayerApi`. `act` is called once every tick, before the game steps.
+pub trait Strategy: Send {
+    fn act(&mut self, tick: u64, api: &mut dyn PlayerApi);
+}
+
+/// One line of a script file: an action to submit at a given tick. The player of the action is
+/// replaced by the player running the script.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ScriptEntry {
+    pub tick: u64,
+    pub action: GameAction,
+}
+
+/// The strategies that can be chosen by name, e.g. with `--bot`.
+#[derive(Clone, Debug)]
+pub enum StrategyKind {
+    /// Never acts.
+    Idle,
+    /// Spreads workers over the gatherable resources and sells every full batch.
+    Gatherer,
+    /// Gathers like `Gatherer`, but refines what it gathers and only sells refined goods. Builds a
+    /// workshop to make tools in.
+    Refiner,
+    /// Gathers like `Gatherer`, but spends Iron and Stone on new workers whenever it can pay them.
+    Expander,
+    /// Grows its workforce to a target size while refining, keeping some raw resources back as
+    /// inputs. Once grown, builds a workshop and staffs its refining buildings.
+    Balanced,
+    /// Gathers and holds on to its goods until the market pays more than it has lately.
+    Trader,
+    /// Submits the actions of a script file at their ticks.
+    Script(String, Arc<Vec<ScriptEntry>>),
+    /// Runs a bot script every tick.
+    Bot(String, Arc<Program>),
+}
+
+impl StrategyKind {
+    pub fn build(&self) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Idle => Box::new(Idle),
+            StrategyKind::Gatherer => Box::new(Gatherer),
+            StrategyKind::Refiner => Box::new(Refiner),
+            StrategyKind::Expander => Box::new(Expander),
+            StrategyKind::Balanced => Box::new(Balanced),
+            StrategyKind::Trader => Box::new(Trader {
+                averages: [None; enum_iterator::cardinality::<SellItem>()],
+            }),
+            StrategyKind::Script(_, entries) => Box::new(Script {
+                entries: entries.clone(),
+                next: 0,
+            }),
+            StrategyKind::Bot(_, program) => Box::new(ScriptBot::new(program.clone())),
+        }
+    }
+}
+
+/// Parses `idle`, `gatherer`, `refiner`, `expander`, `balanced`, `trader`, `script:<path>` or
+/// `bot:<path>`.
+impl FromStr for StrategyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "idle" => Ok(StrategyKind::Idle),
+            "gatherer" => Ok(StrategyKind::Gatherer),
+            "refiner" => Ok(StrategyKind::Refiner),
+            "expander" => Ok(StrategyKind::Expander),
+            "balanced" => Ok(StrategyKind::Balanced),
+            "trader" => Ok(StrategyKind::Trader),
+            _ if s.starts_with("bot:") => {
+                let path = &s["bot:".len()..];
+                let program = load_bot(path)?;
+                Ok(StrategyKind::Bot(path.to_owned(), Arc::new(program)))
+            }
+            _ => {
+                let path = s
+                    .strip_prefix("script:")
+                    .ok_or_else(|| format!("unknown strategy {}", s))?;
+                let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                let mut entries = text
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(i, line)| {
+                        serde_json::from_str::<ScriptEntry>(line)
+                            .map_err(|e| format!("{}:{}: {}", path, i + 1, e))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                entries.sort_by_key(|entry| entry.tick);
+                Ok(StrategyKind::Script(path.to_owned(), Arc::new(entries)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for StrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StrategyKind::Idle => write!(f, "idle"),
+            StrategyKind::Gatherer => write!(f, "gatherer"),
+            StrategyKind::Refiner => write!(f, "refiner"),
+            StrategyKind::Expander => write!(f, "expander"),
+            StrategyKind::Balanced => write!(f, "balanced"),
+            StrategyKind::Trader => write!(f, "trader"),
+            StrategyKind::Script(path, _) => write!(f, "script:{}", path),
+            StrategyKind::Bot(path, _) => write!(f, "bot:{}", path),
+        }
+    }
+}
+
+/// Reads and parses the bot script at `path`.
+pub fn load_bot(path: &str) -> Result<Program, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    Program::parse(&text).map_err(|e| format!("{}:{}: {}", path, e.line, e.message))
+}
+
+fn amount(quantities: &[ResourceQuantity], resource: Resource) -> u32 {
+    quantities
+        .iter()
+        .find(|q| q.resource == resource)
+        .map_or(0, |q| q.amount)
+}
+
+/// A deposit yielding at most this percentage is about to run out, so a new one is looked for.
+const EXPLORE_BELOW_YIELD: u32 = 50;
+
+/// Takes workers off resources that have no deposit left and explores for the resources whose
+/// deposits are running low, as long as that leaves the money for wages unless there is no
+/// deposit left at all. A large workforce may need several explorations at once to keep up.
+fn tend_deposits(api: &mut dyn PlayerApi) {
+    let me = api.get_player();
+    let deposits = content::get().get_deposits();
+    let mut money = me.money;
+    for res in Resource::gatherable() {
+        let workers = amount(&me.gathering_workers, res);
+        let best_yield = me
+            .deposits
+            .iter()
+            .filter(|d| d.resource == res)
+            .map(|d| d.yield_percent)
+            .max();
+        if best_yield.is_none() {
+            for _ in 0..workers {
+                let _ = api.deallocate_worker(res);
+            }
+        }
+        // What is left, counting each exploration under way as its smallest possible find,
+        // against what the workers gather in two explorations' time.
+        let exploring = me.explorations.iter().filter(|e| e.resource == res).count() as u64;
+        let reserve = me
+            .deposits
+            .iter()
+            .filter(|d| d.resource == res)
+            .map(|d| d.reserve as u64)
+            .sum::<u64>()
+            + exploring * deposits.min_reserve as u64;
+        let needed = (workers.max(1) as u64) * deposits.explore_time.ticks * 2;
+        let running_low = exploring == 0 && best_yield.is_none_or(|y| y <= EXPLORE_BELOW_YIELD);
+        let budget = if best_yield.is_none() {
+            deposits.explore_cost
+        } else {
+            deposits.explore_cost + me.wages_due
+        };
+        if (running_low || reserve < needed) && money >= budget && api.explore(res).is_ok() {
+            money -= deposits.explore_cost;
+        }
+    }
+}
+
+/// Puts every idle worker on the gatherable resource with a deposit that has the fewest workers.
+fn allocate_idle(api: &mut dyn PlayerApi, me: &PlayerSnapshot) {
+    let mut workers: Vec<(Resource, u32)> = Resource::gatherable()
+        .filter(|res| me.deposits.iter().any(|d| d.resource == *res))
+        .map(|res| (res, amount(&me.gathering_workers, res)))
+        .collect();
+    for _ in 0..me.idle_workers {
+        let Some(least) = workers.iter_mut().min_by_key(|(_, count)| *count) else {
+            return;
+        };
+        if api.allocate_worker(least.0).is_err() {
+            return;
+        }
+        least.1 += 1;
+    }
+}
+
+/// Queues the first item of `items` that can be afforded in every finished building of the
+/// player that has nothing queued.
+fn keep_buildings_busy(api: &mut dyn PlayerApi, me: &PlayerSnapshot, items: &[ProductionItem]) {
+    for building in me
+        .buildings
+        .iter()
+        .filter(|b| b.construction_ticks == 0 && b.queue.is_empty())
+    {
+        for item in items
+            .iter()
+            .filter(|item| item.get_building() == building.kind)
+        {
+            if api.produce(*item).is_ok() {
+                break;
+            }
+        }
+    }
+}
+
+/// Starts constructing a building of `kind` unless the player has one already or could not
+/// maintain it on top of its wages.
+fn build_once(api: &mut dyn PlayerApi, me: &PlayerSnapshot, kind: BuildingKind) {
+    if me.buildings.iter().all(|b| b.kind != kind)
+        && me.money >= me.wages_due + kind.get_maintenance()
+    {
+        let _ = api.build(kind);
+    }
+}
+
+/// Whether the player lacks the money to pay its workers at the next payday.
+fn short_of_wages(me: &PlayerSnapshot) -> bool {
+    me.money < me.wages_due
+}
+
+/// Sells a batch of every item in `items` that the player holds more than `reserve` units
+/// beyond a batch of.
+fn sell_batches(
+    api: &mut dyn PlayerApi,
+    me: &PlayerSnapshot,
+    items: impl Iterator<Item = SellItem>,
+    reserve: u32,
+) {
+    for item in items {
+        if amount(&me.stockpile, item.get_resource()) >= item.get_batch_size() + reserve {
+            let _ = api.sell(item);
+        }
+    }
+}
+
+struct Idle;
+
+impl Strategy for Idle {
+    fn act(&mut self, _: u64, _: &mut dyn PlayerApi) {}
+}
+
+struct Gatherer;
+
+impl Strategy for Gatherer {
+    fn act(&mut self, _: u64, api: &mut dyn PlayerApi) {
+        tend_deposits(api);
+        let me = api.get_player();
+        allocate_idle(api, &me);
+        sell_batches(api, &me, enum_iterator::all::<SellItem>(), 0);
+    }
+}
+
+struct Refiner;
+
+const REFINING_ORDER: [ProductionItem; 4] = [
+    ProductionItem::Tool,
+    ProductionItem::IronPlate,
+    ProductionItem::CopperWire,
+    ProductionItem::Brick,
+];
+
+impl Strategy for Refiner {
+    fn act(&mut self, _: u64, api: &mut dyn PlayerApi) {
+        tend_deposits(api);
+        let me = api.get_player();
+        allocate_idle(api, &me);
+        build_once(api, &me, BuildingKind::Workshop);
+        keep_buildings_busy(api, &me, &REFINING_ORDER);
+        // Raw resources are only sold to make the payroll.
+        let short = short_of_wages(&me);
+        let items = enum_iterator::all::<SellItem>()
+            .filter(|item| short || !item.get_resource().is_gatherable());
+        sell_batches(api, &me, items, 0);
+    }
+}
+
+struct Expander;
+
+/// What the expander keeps in stock towards its next worker.
+const EXPANDER_RESERVE: u32 = 100;
+/// The expander only takes on another worker while it could pay this many paydays in advance.
+const EXPANDER_PAYDAYS: u64 = 2;
+
+impl Strategy for Expander {
+    fn act(&mut self, _: u64, api: &mut dyn PlayerApi) {
+        tend_deposits(api);
+        let me = api.get_player();
+        allocate_idle(api, &me);
+        let short = short_of_wages(&me);
+        // A full store means the market is not taking what is gathered already.
+        let full = Resource::gatherable()
+            .any(|res| amount(&me.stockpile, res) + EXPANDER_RESERVE >= amount(&me.capacity, res));
+        if !full && me.money >= EXPANDER_PAYDAYS * me.wages_due {
+            let items = [ProductionItem::WorkerIron, ProductionItem::WorkerStone];
+            keep_buildings_busy(api, &me, &items);
+        }
+        let reserve = if short { 0 } else { EXPANDER_RESERVE };
+        sell_batches(api, &me, enum_iterator::all::<SellItem>(), reserve);
+    }
+}
+
+struct Balanced;
+
+const BALANCED_TARGET_WORKERS: u32 = 12;
+/// The raw resources the balanced producer keeps for refining.
+const BALANCED_RESERVE: u32 = 200;
+
+impl Strategy for Balanced {
+    fn act(&mut self, _: u64, api: &mut dyn PlayerApi) {
+        tend_deposits(api);
+        let me = api.get_player();
+        let workers = me.idle_workers
+            + me.gathering_workers.iter().map(|q| q.amount).sum::<u32>()
+            + me.buildings.iter().map(|b| b.staff).sum::<u32>();
+        if workers < BALANCED_TARGET_WORKERS {
+            let items = [ProductionItem::WorkerIron, ProductionItem::WorkerStone];
+            keep_buildings_busy(api, &me, &items);
+        } else {
+            build_once(api, &me, BuildingKind::Workshop);
+            staff_refining(api, &me);
+        }
+        let me = api.get_player();
+        allocate_idle(api, &me);
+        keep_buildings_busy(api, &me, &REFINING_ORDER);
+        let (raw, refined): (Vec<_>, Vec<_>) =
+            enum_iterator::all::<SellItem>().partition(|item| item.get_resource().is_gatherable());
+        sell_batches(api, &me, refined.into_iter(), 0);
+        let reserve = if short_of_wages(&me) {
+            0
+        } else {
+            BALANCED_RESERVE
+        };
+        sell_batches(api, &me, raw.into_iter(), reserve);
+    }
+}
+
+/// Keeps a worker in every finished building that refines, taking one off the resource with the
+/// most gatherers when nobody is idle.
+fn staff_refining(api: &mut dyn PlayerApi, me: &PlayerSnapshot) {
+    for building in me.buildings.iter().filter(|b| {
+        b.construction_ticks == 0
+            && b.staff == 0
+            && b.slots > 0
+            && REFINING_ORDER
+                .iter()
+                .any(|item| item.get_building() == b.kind)
+    }) {
+        if api.get_idle_workers() == 0 {
+            let busiest = me
+                .gathering_workers
+                .iter()
+                .max_by_key(|q| q.amount)
+                .filter(|q| q.amount > 0);
+            if let Some(busiest) = busiest {
+                let _ = api.deallocate_worker(busiest.resource);
+            }
+        }
+        let _ = api.staff_building(building.kind);
+    }
+}
+
+/// How quickly the trader's idea of a normal price follows the market.
+const TRADER_SMOOTHING: f64 = 0.05;
+/// How far above its normal price a bid must be for the trader to sell.
+const TRADER_MARGIN: f64 = 1.05;
+/// The trader sells regardless of price once it holds this many batches, or when it needs the
+/// money for wages.
+const TRADER_MAX_BATCHES: u32 = 5;
+
+struct Trader {
+    averages: [Option<f64>; enum_iterator::cardinality::<SellItem>()],
+}
+
+impl Strategy for Trader {
+    fn act(&mut self, _: u64, api: &mut dyn PlayerApi) {
+        tend_deposits(api);
+        let me = api.get_player();
+        allocate_idle(api, &me);
+        let markets = api.get_markets();
+        let short = short_of_wages(&me);
+        for item in enum_iterator::all::<SellItem>() {
+            let resource = item.get_resource();
+            let Some(bid) = markets
+                .iter()
+                .find(|m| m.resource == resource)
+                .and_then(|m| m.bids.first())
+                .map(|level| level.price as f64)
+            else {
+                continue;
+            };
+            let average = self.averages[item as usize].get_or_insert(bid);
+            let good_price = bid >= *average * TRADER_MARGIN;
+            *average += (bid - *average) * TRADER_SMOOTHING;
+
+            let held = amount(&me.stockpile, resource);
+            let batch = item.get_batch_size();
+            if held >= batch && (good_price || short || held >= batch * TRADER_MAX_BATCHES) {
+                let _ = api.sell(item);
+            }
+        }
+    }
+}
+
+struct Script {
+    entries: Arc<Vec<ScriptEntry>>,
+    next: usize,
+}
+
+impl Strategy for Script {
+    fn act(&mut self, tick: u64, api: &mut dyn PlayerApi) {
+        while let Some(entry) = self.entries.get(self.next) {
+            if entry.tick > tick {
+                break;
+            }
+            let player = api.get_player_id();
+            let _ = api.submit(entry.action.with_player(player));
+            self.next += 1;
+        }
+    }
+}
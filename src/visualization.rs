@@ -6,18 +6,25 @@ use ratatui::{
     text::Span,
     widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table, TableState, Tabs},
 };
-use std::{
-    convert::{TryFrom, TryInto},
-    fmt,
-};
+use std::{collections::BTreeMap, convert::TryFrom, fmt};
 
 use enum_iterator::Sequence;
 
-use crate::game_state::{GameAction, GameState};
+use crate::bank::{LoanId, RATE_SCALE};
+use crate::building::{BuildingKind, ProductionId};
+use crate::buy::BuyItem;
+use crate::clock::GameSpeed;
+use crate::content;
+use crate::deal::{Bundle, ContractTerms, Deal, DealId};
+use crate::game_state::{Duration, GameAction, GameState};
+use crate::government::Levy;
 use crate::input::InputAction;
+use crate::market::{Participant, Side};
+use crate::npc::NpcItem;
 use crate::player::PlayerId;
 use crate::production::ProductionItem;
-use crate::resource::Resource;
+use crate::resource::{Resource, ResourceAmount};
+use crate::rules::{Condition, Quantity, Rule, RuleAction};
 use crate::sell::{SellItem, Trade};
 
 #[derive(Clone, Copy, Debug, Sequence, PartialEq)]
@@ -26,6 +33,13 @@ pub enum TabType {
     Help = 1,
     Production = 2,
     Sell = 3,
+    Market = 4,
+    Buy = 5,
+    Buildings = 6,
+    Automation = 7,
+    Deals = 8,
+    Bank = 9,
+    Script = 10,
 }
 
 impl TabType {
@@ -35,6 +49,13 @@ impl TabType {
             TabType::Help => b'h',
             TabType::Production => b'd',
             TabType::Sell => b's',
+            TabType::Market => b'm',
+            TabType::Buy => b'b',
+            TabType::Buildings => b'u',
+            TabType::Automation => b'a',
+            TabType::Deals => b'o',
+            TabType::Bank => b'k',
+            TabType::Script => b't',
         }
     }
 }
@@ -48,6 +69,13 @@ impl TryFrom<usize> for TabType {
             1 => Ok(TabType::Help),
             2 => Ok(TabType::Production),
             3 => Ok(TabType::Sell),
+            4 => Ok(TabType::Market),
+            5 => Ok(TabType::Buy),
+            6 => Ok(TabType::Buildings),
+            7 => Ok(TabType::Automation),
+            8 => Ok(TabType::Deals),
+            9 => Ok(TabType::Bank),
+            10 => Ok(TabType::Script),
             _ => Err(()),
         }
     }
@@ -80,6 +108,11 @@ impl WrappingTableState {
         self.state.selected().unwrap()
     }
 
+    /// The value of a table listing every value of `T` in order at the selected row.
+    fn get_selected<T: Sequence>(&self) -> T {
+        enum_iterator::all::<T>().nth(self.get_row()).unwrap()
+    }
+
     fn next(&mut self) {
         if self.min == self.max {
             return;
@@ -90,6 +123,14 @@ impl WrappingTableState {
         ));
     }
 
+    /// Changes the number of rows, keeping the selection on the last row if it falls outside.
+    fn resize(&mut self, max: usize) {
+        self.max = max;
+        let curr = self.state.selected().unwrap();
+        self.state
+            .select(Some(curr.min(max.saturating_sub(1)).max(self.min)));
+    }
+
     fn new(min: usize, max: usize) -> Self {
         let mut ret = WrappingTableState {
             state: TableState::default(),
@@ -119,7 +160,7 @@ struct ResourceTab {
 impl Default for ResourceTab {
     fn default() -> Self {
         ResourceTab {
-            worker_selected: WrappingTableState::new(1, Resource::count() + 1),
+            worker_selected: WrappingTableState::new(1, Resource::gatherable().count() + 1),
         }
     }
 }
@@ -128,10 +169,22 @@ impl Tab for ResourceTab {
     fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
         let main_blocks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(area.width - 30), Constraint::Max(30)].as_ref())
+            .constraints(
+                [
+                    Constraint::Length(area.width.saturating_sub(40)),
+                    Constraint::Max(40),
+                ]
+                .as_ref(),
+            )
             .split(area);
+        let left_blocks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(main_blocks[0]);
         let rt = state.resources_as_table();
-        f.render_widget(rt, main_blocks[0]);
+        f.render_widget(rt, left_blocks[0]);
+        let dt = state.player_deposits_as_table(player);
+        f.render_widget(dt, left_blocks[1]);
         let wt = state.player_workers_as_table(player);
         f.render_stateful_widget(wt, main_blocks[1], self.worker_selected.get_mut());
     }
@@ -147,15 +200,17 @@ impl Tab for ResourceTab {
                 None
             }
             InputAction::Decrease => {
-                let resource =
-                    <_ as TryInto<Resource>>::try_into(self.worker_selected.get_row() - 1).unwrap();
+                let resource = Resource::gatherable().nth(self.worker_selected.get_row() - 1)?;
                 Some(GameAction::DeallocateWorker(player, resource))
             }
             InputAction::Increase => {
-                let resource =
-                    <_ as TryInto<Resource>>::try_into(self.worker_selected.get_row() - 1).unwrap();
+                let resource = Resource::gatherable().nth(self.worker_selected.get_row() - 1)?;
                 Some(GameAction::AllocateWorker(player, resource))
             }
+            InputAction::Explore => {
+                let resource = Resource::gatherable().nth(self.worker_selected.get_row() - 1)?;
+                Some(GameAction::Explore(player, resource))
+            }
             _ => None,
         }
     }
@@ -172,8 +227,19 @@ impl Tab for HelpTab {
             .split(area);
         let overview = Paragraph::new(concat!(
             "Control the allocation of your workers to different resources",
-            " using the arrow keys. Balance your economy to produce what you need.",
-            " Sell resources for money."
+            " using the arrow keys. Balance your economy to produce what you need,",
+            " and refine raw resources into goods that are worth more.",
+            " Sell resources for money on the market, where your goods compete with",
+            " the orders of other players, and buy the resources you lack.",
+            " Refining takes buildings: construct more of them to produce in parallel,",
+            " and staff them with workers to produce faster.",
+            " Keep enough money to pay your workers and maintain your buildings,",
+            " and explore for new deposits before the ones you gather from run dry.",
+            " Rules on the Automation tab can take care of routine actions for you,",
+            " and the Deals tab trades directly with other players.",
+            " The Bank lends money against your net worth and pays interest on deposits,",
+            " but a player who misses too many installments goes bankrupt.",
+            " The government taxes sales and stock, and spends its revenue on public works."
         ))
         .block(
             Block::default()
@@ -184,7 +250,23 @@ impl Tab for HelpTab {
         );
         f.render_widget(overview, blocks[0]);
 
-        let hotkeys = [("p", "Toggle pause"), ("q", "Exit program")];
+        let hotkeys = [
+            ("p", "Toggle pause"),
+            ("w", "Quick-save"),
+            ("e", "Explore for the selected resource"),
+            (
+                "Tab",
+                "Switch between recipes and queue, or a list and its editor",
+            ),
+            ("c", "Cancel the selected item, rule or offer"),
+            ("l", "Queue: loop the selected item"),
+            ("< >", "Queue: move the selected item"),
+            ("+ -", "Speed up / slow down the game"),
+            ("n", "Step one tick while paused"),
+            ("[ ]", "Replay: seek backward / forward"),
+            ("v", "Replay: view next player"),
+            ("q", "Exit program"),
+        ];
         let table = Table::new(
             hotkeys
                 .iter()
@@ -208,35 +290,63 @@ impl Tab for HelpTab {
 
 struct ProductionTab {
     selected: WrappingTableState,
+    queue_selected: WrappingTableState,
+    /// Whether the arrow keys work on the queue rather than on the recipes.
+    queue_focused: bool,
+    /// The id, position in its building and repeat flag of every queued item, as last drawn.
+    queue: Vec<(ProductionId, usize, bool)>,
 }
 
 impl Default for ProductionTab {
     fn default() -> Self {
         ProductionTab {
             selected: WrappingTableState::new(0, enum_iterator::cardinality::<ProductionItem>()),
+            queue_selected: WrappingTableState::new(0, 0),
+            queue_focused: false,
+            queue: Vec::new(),
         }
     }
 }
 
-const TABLE_COLS: usize = enum_iterator::cardinality::<Resource>() + 1;
+const TABLE_COLS: usize = enum_iterator::cardinality::<Resource>() + 3;
 const TABLE_WIDTHS: &[Constraint] = &[Constraint::Ratio(1, TABLE_COLS as u32); TABLE_COLS];
 
 impl Tab for ProductionTab {
-    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
-        let player = state.get_player(player);
+    fn draw(&mut self, f: &mut Frame, area: Rect, player_id: PlayerId, state: &GameState) {
+        let player = state.get_player(player_id);
         let player_stockpile = player.get_stockpile();
-        let header =
-            Row::new(std::iter::once(Cell::from("Item")).chain(Resource::names().map(Cell::from)));
+        let header = Row::new(
+            std::iter::once(Cell::from("Item"))
+                .chain(std::iter::once(Cell::from("Made in")))
+                .chain(Resource::names().map(Cell::from))
+                .chain(std::iter::once(Cell::from("Output"))),
+        );
         let content = enum_iterator::all::<ProductionItem>().map(|item| {
-            Row::new(
-                std::iter::once(Cell::from(item.to_string())).chain(
-                    item.get_cost()
-                        .iter()
-                        .zip(player_stockpile.iter())
-                        .map(|(cost, available)| {
-                            Cell::from(cost.to_string() + " / " + &available.to_string())
+            let output = item.get_output();
+            let training = item.get_training();
+            let output = enum_iterator::all::<Resource>()
+                .filter(|res| output.get(*res) > 0)
+                .map(|res| format!("{} {}", output.get(res), res))
+                .chain((item.get_workers() > 0).then(|| format!("{} Worker", item.get_workers())))
+                .chain(
+                    enum_iterator::all::<Resource>()
+                        .filter(|res| training.get(*res) > 0)
+                        .map(|res| {
+                            let secs = training.get(res) as u64 / Duration::TICKS_PER_SEC;
+                            format!("{}s {} training", secs, res)
                         }),
-                ),
+                )
+                .collect::<Vec<_>>()
+                .join(", ");
+            Row::new(
+                std::iter::once(Cell::from(item.to_string()))
+                    .chain(std::iter::once(Cell::from(item.get_building().to_string())))
+                    .chain(item.get_cost().iter().zip(player_stockpile.iter()).map(
+                        |(cost, available)| {
+                            Cell::from(cost.to_string() + " / " + available.to_string().as_str())
+                        },
+                    ))
+                    .chain(std::iter::once(Cell::from(output))),
             )
         });
         let table = Table::new(content, TABLE_WIDTHS.iter())
@@ -253,30 +363,204 @@ impl Tab for ProductionTab {
 
         let blocks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(10), Constraint::Percentage(90)].as_ref())
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
             .split(area);
+        let top_blocks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(blocks[0]);
 
-        let current_production_string = player.get_current_production().map_or(
-            "No current production".to_owned(),
-            |(item, duration)| {
-                "Current production: ".to_owned()
-                    + item.to_string().as_str()
-                    + ", remaining ticks: "
-                    + duration.ticks.to_string().as_str()
-            },
+        let mut positions = BTreeMap::new();
+        self.queue = player
+            .get_production_queue()
+            .map(|(building, queued)| {
+                let position = positions.entry(building).or_insert(0);
+                *position += 1;
+                (queued.id, *position - 1, queued.repeat)
+            })
+            .collect();
+        self.queue_selected.resize(self.queue.len());
+
+        // Only the focused table shows its selection.
+        let mut unfocused = TableState::default();
+        let (recipes_state, queue_state) = if self.queue_focused {
+            (&mut unfocused, self.queue_selected.get_mut())
+        } else {
+            (self.selected.get_mut(), &mut unfocused)
+        };
+        f.render_widget(state.player_buildings_as_table(player_id), top_blocks[0]);
+        f.render_stateful_widget(
+            state.player_queue_as_table(player_id),
+            top_blocks[1],
+            queue_state,
         );
-        let current_production_display = Paragraph::new(current_production_string).block(
+        f.render_stateful_widget(table, blocks[1], recipes_state);
+    }
+
+    fn handle_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        if let InputAction::SwitchFocus = input {
+            self.queue_focused = !self.queue_focused;
+            return None;
+        }
+        if self.queue_focused {
+            return self.handle_queue_input(player, input);
+        }
+        match input {
+            InputAction::MoveUp => {
+                self.selected.prev();
+                None
+            }
+            InputAction::MoveDown => {
+                self.selected.next();
+                None
+            }
+            InputAction::PerformAction => {
+                let item = self.selected.get_selected::<ProductionItem>();
+                Some(GameAction::Produce(player, item))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ProductionTab {
+    fn handle_queue_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        let selected = self.queue.get(self.queue_selected.get_row()).copied();
+        match input {
+            InputAction::MoveUp => {
+                self.queue_selected.prev();
+                None
+            }
+            InputAction::MoveDown => {
+                self.queue_selected.next();
+                None
+            }
+            InputAction::Decrease => {
+                let (id, position, _) = selected?;
+                let position = position.checked_sub(1)?;
+                Some(GameAction::MoveProduction(player, id, position))
+            }
+            InputAction::Increase => {
+                let (id, position, _) = selected?;
+                Some(GameAction::MoveProduction(player, id, position + 1))
+            }
+            InputAction::CancelItem => {
+                let (id, _, _) = selected?;
+                Some(GameAction::CancelProduction(player, id))
+            }
+            InputAction::ToggleRepeat => {
+                let (id, _, repeat) = selected?;
+                Some(GameAction::RepeatProduction(player, id, !repeat))
+            }
+            _ => None,
+        }
+    }
+}
+
+struct BuildingsTab {
+    selected: WrappingTableState,
+}
+
+impl Default for BuildingsTab {
+    fn default() -> Self {
+        BuildingsTab {
+            selected: WrappingTableState::new(0, enum_iterator::cardinality::<BuildingKind>()),
+        }
+    }
+}
+
+impl Tab for BuildingsTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
+        let p = state.get_player(player);
+        let header = Row::new(vec![
+            "Building",
+            "Cost",
+            "Time",
+            "Maintenance",
+            "Effect",
+            "Owned",
+            "Staff",
+        ]);
+        let content = enum_iterator::all::<BuildingKind>().map(|kind| {
+            let cost = kind.get_cost();
+            let cost = enum_iterator::all::<Resource>()
+                .filter(|res| cost.get(*res) > 0)
+                .map(|res| format!("{} {}", cost.get(res), res))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let effect = enum_iterator::all::<ProductionItem>()
+                .filter(|item| item.get_building() == kind)
+                .map(|item| item.to_string())
+                .chain((kind.get_storage() > 0).then(|| format!("+{} storage", kind.get_storage())))
+                .chain(
+                    (kind.get_gather_bonus() > 0)
+                        .then(|| format!("+{}% gathering", kind.get_gather_bonus())),
+                )
+                .collect::<Vec<_>>()
+                .join(", ");
+            let owned = || {
+                p.get_buildings()
+                    .zip(0..)
+                    .filter(move |(b, _)| b.get_kind() == kind)
+            };
+            let finished = owned().filter(|(b, _)| b.is_finished()).count();
+            let staff: u32 = owned().map(|(_, id)| p.get_staff(id)).sum();
+            let slots = finished as u32 * kind.get_slots();
+            let (time, cost) = if kind.is_buildable() {
+                let secs = kind.get_construction_time().ticks / Duration::TICKS_PER_SEC;
+                (format!("{}s", secs), cost)
+            } else {
+                ("-".to_owned(), "-".to_owned())
+            };
+            let dec_symb = if staff > 0 { "<" } else { " " };
+            let inc_symb = if staff < slots { ">" } else { " " };
+            Row::new(vec![
+                Cell::from(kind.to_string()),
+                Cell::from(cost),
+                Cell::from(time),
+                Cell::from(kind.get_maintenance().to_string()),
+                Cell::from(effect),
+                Cell::from(format!("{} / {}", finished, owned().count())),
+                Cell::from(format!("{} {} / {} {}", dec_symb, staff, slots, inc_symb)),
+            ])
+        });
+        let table = Table::new(
+            content,
+            [
+                Constraint::Percentage(12),
+                Constraint::Percentage(20),
+                Constraint::Percentage(6),
+                Constraint::Percentage(10),
+                Constraint::Percentage(32),
+                Constraint::Percentage(8),
+                Constraint::Percentage(12),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
             Block::default()
-                .style(Style::default().bg(Color::DarkGray))
                 .borders(Borders::ALL)
-                .border_type(BorderType::Thick),
-        );
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from(
+                    "Enter: construct, left / right: take off / add staff",
+                )),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
 
-        f.render_widget(current_production_display, blocks[0]);
-        f.render_stateful_widget(table, blocks[1], self.selected.get_mut());
+        let blocks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+            .split(area);
+        f.render_stateful_widget(table, blocks[0], self.selected.get_mut());
+        f.render_widget(state.player_buildings_as_table(player), blocks[1]);
     }
 
     fn handle_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        let kind = self.selected.get_selected::<BuildingKind>();
         match input {
             InputAction::MoveUp => {
                 self.selected.prev();
@@ -286,11 +570,324 @@ impl Tab for ProductionTab {
                 self.selected.next();
                 None
             }
-            InputAction::PerformAction => {
-                let item =
-                    <_ as TryInto<ProductionItem>>::try_into(self.selected.get_row()).unwrap();
-                Some(GameAction::Produce(player, item))
+            InputAction::PerformAction => Some(GameAction::Build(player, kind)),
+            InputAction::Decrease => Some(GameAction::UnstaffBuilding(player, kind)),
+            InputAction::Increase => Some(GameAction::StaffBuilding(player, kind)),
+            _ => None,
+        }
+    }
+}
+
+/// The parts of a rule that can be set in the editor of the Automation tab.
+#[derive(Clone, Copy, Debug, PartialEq, Sequence)]
+enum RuleField {
+    Subject,
+    Comparison,
+    Value,
+    Action,
+    Target,
+    Workers,
+}
+
+impl RuleField {
+    fn get_label(&self) -> &'static str {
+        match self {
+            RuleField::Subject => "When",
+            RuleField::Comparison => "Is",
+            RuleField::Value => "Value",
+            RuleField::Action => "Then",
+            RuleField::Target => "Of",
+            RuleField::Workers => "Workers",
+        }
+    }
+}
+
+/// The rule being put together in the editor. A subject of `None` makes a rule that always
+/// fires.
+struct RuleDraft {
+    subject: Option<Quantity>,
+    at_least: bool,
+    value: u64,
+    action: RuleAction,
+}
+
+impl Default for RuleDraft {
+    fn default() -> Self {
+        RuleDraft {
+            subject: Some(Quantity::Stock(Resource::Iron)),
+            at_least: true,
+            value: 500,
+            action: RuleAction::Sell(SellItem::Iron),
+        }
+    }
+}
+
+impl RuleDraft {
+    fn get_rule(&self) -> Rule {
+        let condition = match self.subject {
+            None => Condition::Always,
+            Some(quantity) if self.at_least => Condition::AtLeast(quantity, self.value),
+            Some(quantity) => Condition::AtMost(quantity, self.value),
+        };
+        Rule {
+            condition,
+            action: self.action,
+            enabled: true,
+        }
+    }
+
+    fn get_value(&self, field: RuleField) -> String {
+        match (field, self.subject, self.action) {
+            (RuleField::Subject, None, _) => "always".to_owned(),
+            (RuleField::Subject, Some(quantity), _) => quantity.to_string(),
+            (RuleField::Comparison | RuleField::Value, None, _) => "-".to_owned(),
+            (RuleField::Comparison, _, _) => {
+                if self.at_least { "at least" } else { "at most" }.to_owned()
+            }
+            (RuleField::Value, _, _) => self.value.to_string(),
+            (RuleField::Action, _, action) => match action {
+                RuleAction::Sell(_) => "sell",
+                RuleAction::Buy(_) => "buy",
+                RuleAction::Produce(_) => "produce",
+                RuleAction::KeepWorkers(_, _) => "keep workers",
+            }
+            .to_owned(),
+            (RuleField::Target, _, action) => match action {
+                RuleAction::Sell(item) => item.to_string(),
+                RuleAction::Buy(item) => item.to_string(),
+                RuleAction::Produce(item) => item.to_string(),
+                RuleAction::KeepWorkers(resource, _) => resource.to_string(),
+            },
+            (RuleField::Workers, _, RuleAction::KeepWorkers(_, count)) => count.to_string(),
+            (RuleField::Workers, _, _) => "-".to_owned(),
+        }
+    }
+
+    /// Steps `field` to its next value, or its previous one if `forward` is false.
+    fn change(&mut self, field: RuleField, forward: bool) {
+        fn cycle<T: Sequence>(x: &T, forward: bool) -> T {
+            if forward {
+                enum_iterator::next_cycle(x)
+            } else {
+                enum_iterator::previous_cycle(x)
+            }
+        }
+        match field {
+            RuleField::Subject => loop {
+                self.subject = cycle(&self.subject, forward);
+                // Only gatherable resources have workers on them.
+                if !matches!(self.subject, Some(Quantity::Gathering(res)) if !res.is_gatherable()) {
+                    break;
+                }
+            },
+            RuleField::Comparison => self.at_least = !self.at_least,
+            RuleField::Value => {
+                let step = match self.subject {
+                    Some(Quantity::Stock(_)) => 50,
+                    Some(Quantity::Money) => 500,
+                    _ => 1,
+                };
+                self.value = if forward {
+                    self.value + step
+                } else {
+                    self.value.saturating_sub(step)
+                };
+            }
+            RuleField::Action => {
+                let first_resource = Resource::gatherable().next().unwrap();
+                let first_sell = enum_iterator::first::<SellItem>().unwrap();
+                let first_buy = enum_iterator::first::<BuyItem>().unwrap();
+                let first_item = enum_iterator::first::<ProductionItem>().unwrap();
+                let kinds = [
+                    RuleAction::Sell(first_sell),
+                    RuleAction::Buy(first_buy),
+                    RuleAction::Produce(first_item),
+                    RuleAction::KeepWorkers(first_resource, 1),
+                ];
+                let current = kinds
+                    .iter()
+                    .position(|kind| {
+                        std::mem::discriminant(kind) == std::mem::discriminant(&self.action)
+                    })
+                    .unwrap();
+                let next = if forward {
+                    (current + 1) % kinds.len()
+                } else {
+                    (current + kinds.len() - 1) % kinds.len()
+                };
+                self.action = kinds[next];
+            }
+            RuleField::Target => {
+                self.action = match self.action {
+                    RuleAction::Sell(item) => RuleAction::Sell(cycle(&item, forward)),
+                    RuleAction::Buy(item) => RuleAction::Buy(cycle(&item, forward)),
+                    RuleAction::Produce(item) => RuleAction::Produce(cycle(&item, forward)),
+                    RuleAction::KeepWorkers(mut resource, count) => {
+                        resource = cycle(&resource, forward);
+                        while !resource.is_gatherable() {
+                            resource = cycle(&resource, forward);
+                        }
+                        RuleAction::KeepWorkers(resource, count)
+                    }
+                }
             }
+            RuleField::Workers => {
+                if let RuleAction::KeepWorkers(resource, count) = self.action {
+                    let count = if forward {
+                        count + 1
+                    } else {
+                        count.saturating_sub(1)
+                    };
+                    self.action = RuleAction::KeepWorkers(resource, count);
+                }
+            }
+        }
+    }
+}
+
+struct AutomationTab {
+    selected: WrappingTableState,
+    field_selected: WrappingTableState,
+    /// Whether the arrow keys work on the editor rather than on the list of rules.
+    editor_focused: bool,
+    draft: RuleDraft,
+}
+
+impl Default for AutomationTab {
+    fn default() -> Self {
+        AutomationTab {
+            selected: WrappingTableState::new(0, 0),
+            field_selected: WrappingTableState::new(0, enum_iterator::cardinality::<RuleField>()),
+            editor_focused: false,
+            draft: RuleDraft::default(),
+        }
+    }
+}
+
+impl Tab for AutomationTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
+        let p = state.get_player(player);
+        let rules = p.get_rules();
+        self.selected.resize(rules.len());
+        let header = Row::new(vec!["#", "On", "Rule", "Status"]);
+        let content = rules.iter().enumerate().map(|(i, rule)| {
+            let status = if state.is_rule_pending(player, i) {
+                Cell::from("waiting")
+            } else if let Some(error) = p.get_rule_error(i) {
+                Cell::from(error.to_string()).style(Style::default().fg(Color::LightRed))
+            } else {
+                Cell::from("")
+            };
+            Row::new(vec![
+                Cell::from(i.to_string()),
+                Cell::from(if rule.enabled { "Yes" } else { "No" }),
+                Cell::from(rule.to_string()),
+                status,
+            ])
+        });
+        let list = Table::new(
+            content,
+            [
+                Constraint::Percentage(5),
+                Constraint::Percentage(10),
+                Constraint::Percentage(55),
+                Constraint::Percentage(30),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Rules - Enter: switch on / off, c: remove")),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
+
+        let content = enum_iterator::all::<RuleField>().map(|field| {
+            Row::new(vec![
+                Cell::from(field.get_label()),
+                Cell::from(self.draft.get_value(field)),
+            ])
+        });
+        let editor = Table::new(
+            content,
+            [Constraint::Percentage(30), Constraint::Percentage(70)].iter(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from(format!(
+                    "New rule: {} - left / right: change, Enter: add",
+                    self.draft.get_rule()
+                ))),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
+
+        let blocks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(area);
+        // Only the focused table shows its selection.
+        let mut unfocused = TableState::default();
+        let (list_state, editor_state) = if self.editor_focused {
+            (&mut unfocused, self.field_selected.get_mut())
+        } else {
+            (self.selected.get_mut(), &mut unfocused)
+        };
+        f.render_stateful_widget(list, blocks[0], list_state);
+        f.render_stateful_widget(editor, blocks[1], editor_state);
+    }
+
+    fn handle_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        if let InputAction::SwitchFocus = input {
+            self.editor_focused = !self.editor_focused;
+            return None;
+        }
+        if self.editor_focused {
+            let field = enum_iterator::all::<RuleField>().nth(self.field_selected.get_row())?;
+            return match input {
+                InputAction::MoveUp => {
+                    self.field_selected.prev();
+                    None
+                }
+                InputAction::MoveDown => {
+                    self.field_selected.next();
+                    None
+                }
+                InputAction::Decrease => {
+                    self.draft.change(field, false);
+                    None
+                }
+                InputAction::Increase => {
+                    self.draft.change(field, true);
+                    None
+                }
+                InputAction::PerformAction => {
+                    Some(GameAction::AddRule(player, self.draft.get_rule()))
+                }
+                _ => None,
+            };
+        }
+        let index = self.selected.get_row();
+        match input {
+            InputAction::MoveUp => {
+                self.selected.prev();
+                None
+            }
+            InputAction::MoveDown => {
+                self.selected.next();
+                None
+            }
+            InputAction::PerformAction => Some(GameAction::ToggleRule(player, index)),
+            InputAction::CancelItem => Some(GameAction::RemoveRule(player, index)),
             _ => None,
         }
     }
@@ -332,7 +929,9 @@ impl Tab for SellTab {
                         res.iter()
                             .zip(player_stockpile.iter())
                             .map(|(cost, available)| {
-                                Cell::from(cost.to_string() + " / " + &available.to_string())
+                                Cell::from(
+                                    cost.to_string() + " / " + available.to_string().as_str(),
+                                )
                             }),
                     ),
             )
@@ -363,7 +962,7 @@ impl Tab for SellTab {
                 None
             }
             InputAction::PerformAction => {
-                let item = <_ as TryInto<SellItem>>::try_into(self.selected.get_row()).unwrap();
+                let item = self.selected.get_selected::<SellItem>();
                 Some(GameAction::Sell(player, item))
             }
             _ => None,
@@ -371,6 +970,929 @@ impl Tab for SellTab {
     }
 }
 
+struct BuyTab {
+    selected: WrappingTableState,
+}
+
+impl Default for BuyTab {
+    fn default() -> Self {
+        BuyTab {
+            selected: WrappingTableState::new(0, enum_iterator::cardinality::<BuyItem>()),
+        }
+    }
+}
+
+impl Tab for BuyTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
+        let player = state.get_player(player);
+        let header = Row::new(
+            std::iter::once(Cell::from("Item"))
+                .chain(std::iter::once(Cell::from("Cost")))
+                .chain(Resource::names().map(Cell::from)),
+        );
+        let content = enum_iterator::all::<BuyItem>().map(|item| {
+            let quote = state
+                .get_market()
+                .get_book(item.get_resource())
+                .quote_buy(item.get_batch_size());
+            let mut goods = ResourceAmount::new();
+            let cost = match quote {
+                Some(quote) => {
+                    *goods.get_mut(item.get_resource()) = quote.quantity;
+                    let cost = quote
+                        .cost
+                        .map_or("too much".to_owned(), |cost| cost.to_string());
+                    cost + " / " + player.get_money().to_string().as_str()
+                }
+                None => "-".to_owned(),
+            };
+            Row::new(
+                std::iter::once(Cell::from(item.to_string()))
+                    .chain(std::iter::once(Cell::from(cost)))
+                    .chain(goods.iter().zip(player.get_stockpile().iter()).map(
+                        |(gain, available)| {
+                            Cell::from(gain.to_string() + " / " + available.to_string().as_str())
+                        },
+                    )),
+            )
+        });
+        let table = Table::new(content, SELL_TABLE_WIDTHS.iter())
+            .header(header)
+            .style(Style::default().fg(Color::White))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Thick)
+                    .style(Style::default().bg(Color::DarkGray)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol(">>");
+
+        f.render_stateful_widget(table, area, self.selected.get_mut());
+    }
+
+    fn handle_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        match input {
+            InputAction::MoveUp => {
+                self.selected.prev();
+                None
+            }
+            InputAction::MoveDown => {
+                self.selected.next();
+                None
+            }
+            InputAction::PerformAction => {
+                let item = self.selected.get_selected::<BuyItem>();
+                Some(GameAction::Buy(player, item))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct MarketTab {}
+
+fn price_to_string(price: Option<u64>) -> String {
+    price.map_or("-".to_owned(), |p| p.to_string())
+}
+
+impl Tab for MarketTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
+        let market = state.get_market();
+        let header = Row::new(
+            ["Resource", "Bid", "Ask", "Mid", "Last"]
+                .iter()
+                .map(|h| Cell::from(*h)),
+        );
+        let content = enum_iterator::all::<Resource>().map(|res| {
+            let book = market.get_book(res);
+            Row::new(vec![
+                Cell::from(res.to_string()),
+                Cell::from(price_to_string(book.best_bid())),
+                Cell::from(price_to_string(book.best_ask())),
+                Cell::from(price_to_string(book.mid_price())),
+                Cell::from(price_to_string(book.get_last_price())),
+            ])
+        });
+        let books = Table::new(content, [Constraint::Ratio(1, 5); 5].iter())
+            .header(header)
+            .style(Style::default().fg(Color::White))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Thick)
+                    .style(Style::default().bg(Color::DarkGray))
+                    .title(Span::from("Markets")),
+            );
+
+        let header = Row::new(
+            ["Order", "Resource", "Side", "Price", "Quantity"]
+                .iter()
+                .map(|h| Cell::from(*h)),
+        );
+        let content = market
+            .orders_of(Participant::Player(player))
+            .map(|(res, order)| {
+                let side = match order.side {
+                    Side::Buy => "Buy",
+                    Side::Sell => "Sell",
+                };
+                Row::new(vec![
+                    Cell::from(order.id.to_string()),
+                    Cell::from(res.to_string()),
+                    Cell::from(side),
+                    Cell::from(order.price.to_string()),
+                    Cell::from(order.quantity.to_string()),
+                ])
+            });
+        let orders = Table::new(content, [Constraint::Ratio(1, 5); 5].iter())
+            .header(header)
+            .style(Style::default().fg(Color::White))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Thick)
+                    .style(Style::default().bg(Color::DarkGray))
+                    .title(Span::from("Your orders")),
+            );
+
+        let government = state.get_government();
+        let revenue = enum_iterator::all::<Levy>().map(|levy| {
+            (
+                format!("{} {}%", levy, levy.get_rate()),
+                government.get_collected(levy).to_string(),
+            )
+        });
+        let bids = market
+            .orders_of(Participant::Government)
+            .map(|(res, order)| {
+                (
+                    format!("Buying {}", res),
+                    format!("{} at {}", order.quantity, order.price),
+                )
+            });
+        let content =
+            std::iter::once(("Treasury".to_owned(), government.get_treasury().to_string()))
+                .chain(revenue)
+                .chain(std::iter::once((
+                    "Public works".to_owned(),
+                    government.get_spent().to_string(),
+                )))
+                .chain(bids)
+                .map(|(label, value)| Row::new(vec![Cell::from(label), Cell::from(value)]));
+        let public = Table::new(
+            content,
+            [Constraint::Percentage(55), Constraint::Percentage(45)].iter(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Government")),
+        );
+
+        let blocks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(area);
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(blocks[1]);
+        f.render_widget(books, blocks[0]);
+        f.render_widget(orders, bottom[0]);
+        f.render_widget(public, bottom[1]);
+    }
+
+    fn handle_input(&mut self, _: PlayerId, _: InputAction) -> Option<GameAction> {
+        None
+    }
+}
+
+/// The parts of a deal that can be set in the editor of the Deals tab.
+#[derive(Clone, Copy, Debug, PartialEq, Sequence)]
+enum DealField {
+    Reply,
+    Partner,
+    Kind,
+    Resource,
+    Quantity,
+    Price,
+    Ticks,
+    Penalty,
+}
+
+impl DealField {
+    fn get_label(&self) -> &'static str {
+        match self {
+            DealField::Reply => "Counter",
+            DealField::Partner => "With",
+            DealField::Kind => "Deal",
+            DealField::Resource => "Resource",
+            DealField::Quantity => "Quantity",
+            DealField::Price => "Money",
+            DealField::Ticks => "Ticks",
+            DealField::Penalty => "Penalty",
+        }
+    }
+}
+
+/// The deals the editor can put together. Any bundle of resources and money can be exchanged
+/// through the API.
+#[derive(Clone, Copy, Debug, PartialEq, Sequence)]
+enum DealKind {
+    Sell,
+    Buy,
+    Supply,
+    Purchase,
+}
+
+impl fmt::Display for DealKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DealKind::Sell => write!(f, "sell for money"),
+            DealKind::Buy => write!(f, "buy for money"),
+            DealKind::Supply => write!(f, "supply contract"),
+            DealKind::Purchase => write!(f, "purchase contract"),
+        }
+    }
+}
+
+/// The deal being put together in the editor. With a `reply` it counters that offer, and goes to
+/// its sender rather than to `partner`.
+struct DealDraft {
+    reply: Option<DealId>,
+    partner: PlayerId,
+    kind: DealKind,
+    resource: Resource,
+    quantity: u32,
+    price: u64,
+    ticks: u64,
+    penalty: u64,
+}
+
+impl Default for DealDraft {
+    fn default() -> Self {
+        DealDraft {
+            reply: None,
+            partner: 1,
+            kind: DealKind::Sell,
+            resource: Resource::Iron,
+            quantity: 100,
+            price: 500,
+            ticks: 100,
+            penalty: 50,
+        }
+    }
+}
+
+impl DealDraft {
+    fn get_deal(&self) -> Deal {
+        let mut resources = ResourceAmount::new();
+        *resources.get_mut(self.resource) = self.quantity;
+        let goods = Bundle {
+            resources,
+            money: 0,
+        };
+        let money = Bundle {
+            resources: ResourceAmount::new(),
+            money: self.price,
+        };
+        let terms = ContractTerms {
+            resource: self.resource,
+            quantity: self.quantity,
+            price: self.price,
+            ticks: self.ticks,
+            penalty: self.penalty,
+        };
+        match self.kind {
+            DealKind::Sell => Deal::Exchange {
+                give: goods,
+                receive: money,
+            },
+            DealKind::Buy => Deal::Exchange {
+                give: money,
+                receive: goods,
+            },
+            DealKind::Supply => Deal::Contract(Side::Sell, terms),
+            DealKind::Purchase => Deal::Contract(Side::Buy, terms),
+        }
+    }
+
+    fn get_action(&self, player: PlayerId) -> GameAction {
+        match self.reply {
+            Some(id) => GameAction::CounterDeal(player, id, self.get_deal()),
+            None => GameAction::ProposeDeal(player, self.partner, self.get_deal()),
+        }
+    }
+
+    fn get_value(&self, field: DealField) -> String {
+        let is_contract = matches!(self.kind, DealKind::Supply | DealKind::Purchase);
+        match field {
+            DealField::Reply => self
+                .reply
+                .map_or("-".to_owned(), |id| format!("offer {}", id)),
+            DealField::Partner if self.reply.is_some() => "sender".to_owned(),
+            DealField::Partner => format!("Player {}", self.partner),
+            DealField::Kind => self.kind.to_string(),
+            DealField::Resource => self.resource.to_string(),
+            DealField::Quantity => self.quantity.to_string(),
+            DealField::Price => self.price.to_string(),
+            DealField::Ticks | DealField::Penalty if !is_contract => "-".to_owned(),
+            DealField::Ticks => self.ticks.to_string(),
+            DealField::Penalty => self.penalty.to_string(),
+        }
+    }
+
+    /// Steps `field` to its next value, or its previous one if `forward` is false. `player` is
+    /// the one making the deal, `players` the number of players and `received` the offers that
+    /// can be countered.
+    fn change(
+        &mut self,
+        field: DealField,
+        forward: bool,
+        player: PlayerId,
+        players: PlayerId,
+        received: &[DealId],
+    ) {
+        let add = |value: u64, step: u64| {
+            if forward {
+                value + step
+            } else {
+                value.saturating_sub(step)
+            }
+        };
+        match field {
+            DealField::Reply => {
+                let choices: Vec<_> = std::iter::once(None)
+                    .chain(received.iter().copied().map(Some))
+                    .collect();
+                let current = choices.iter().position(|c| *c == self.reply).unwrap_or(0);
+                let next = if forward {
+                    (current + 1) % choices.len()
+                } else {
+                    (current + choices.len() - 1) % choices.len()
+                };
+                self.reply = choices[next];
+            }
+            DealField::Partner => {
+                if players < 2 {
+                    return;
+                }
+                loop {
+                    self.partner = if forward {
+                        (self.partner + 1) % players
+                    } else {
+                        (self.partner + players - 1) % players
+                    };
+                    if self.partner != player {
+                        break;
+                    }
+                }
+            }
+            DealField::Kind => {
+                self.kind = if forward {
+                    enum_iterator::next_cycle(&self.kind)
+                } else {
+                    enum_iterator::previous_cycle(&self.kind)
+                };
+            }
+            DealField::Resource => {
+                self.resource = if forward {
+                    enum_iterator::next_cycle(&self.resource)
+                } else {
+                    enum_iterator::previous_cycle(&self.resource)
+                };
+            }
+            DealField::Quantity => self.quantity = add(self.quantity as u64, 10) as u32,
+            DealField::Price => self.price = add(self.price, 10),
+            DealField::Ticks => self.ticks = add(self.ticks, 25),
+            DealField::Penalty => self.penalty = add(self.penalty, 10),
+        }
+    }
+}
+
+struct DealsTab {
+    selected: WrappingTableState,
+    field_selected: WrappingTableState,
+    /// Whether the arrow keys work on the editor rather than on the list of offers.
+    editor_focused: bool,
+    /// The id and sender of every offer listed, as last drawn.
+    offers: Vec<(DealId, PlayerId)>,
+    /// The number of players, as last drawn.
+    players: PlayerId,
+    draft: DealDraft,
+}
+
+impl Default for DealsTab {
+    fn default() -> Self {
+        DealsTab {
+            selected: WrappingTableState::new(0, 0),
+            field_selected: WrappingTableState::new(0, enum_iterator::cardinality::<DealField>()),
+            editor_focused: false,
+            offers: Vec::new(),
+            players: 0,
+            draft: DealDraft::default(),
+        }
+    }
+}
+
+impl Tab for DealsTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
+        let deals = state.get_deals();
+        self.offers = deals
+            .offers_of(player)
+            .map(|offer| (offer.id, offer.from))
+            .collect();
+        self.players = state.players().count() as PlayerId;
+        self.selected.resize(self.offers.len());
+        // An offer that was settled can no longer be countered.
+        if let Some(id) = self.draft.reply {
+            if !self
+                .offers
+                .iter()
+                .any(|(offer, from)| *offer == id && *from != player)
+            {
+                self.draft.reply = None;
+            }
+        }
+
+        let header = Row::new(vec!["#", "From", "To", "Deal"]);
+        let content = deals.offers_of(player).map(|offer| {
+            Row::new(vec![
+                Cell::from(offer.id.to_string()),
+                Cell::from(offer.from.to_string()),
+                Cell::from(offer.to.to_string()),
+                Cell::from(offer.deal.to_string()),
+            ])
+        });
+        let offers = Table::new(
+            content,
+            [
+                Constraint::Percentage(8),
+                Constraint::Percentage(8),
+                Constraint::Percentage(8),
+                Constraint::Percentage(76),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Offers - Enter: accept, c: decline / withdraw")),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
+
+        let header = Row::new(vec!["#", "Contract", "Left", "Missed"]);
+        let content = deals.contracts_of(player).map(|contract| {
+            Row::new(vec![
+                Cell::from(contract.id.to_string()),
+                Cell::from(contract.to_string()),
+                Cell::from(contract.remaining.to_string()),
+                Cell::from(contract.defaults.to_string()),
+            ])
+        });
+        let contracts = Table::new(
+            content,
+            [
+                Constraint::Percentage(8),
+                Constraint::Percentage(72),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Contracts")),
+        );
+
+        let content = enum_iterator::all::<DealField>().map(|field| {
+            Row::new(vec![
+                Cell::from(field.get_label()),
+                Cell::from(self.draft.get_value(field)),
+            ])
+        });
+        let title = match self.draft.reply {
+            Some(id) => format!("Counter offer {}: {}", id, self.draft.get_deal()),
+            None => format!(
+                "Offer Player {}: {}",
+                self.draft.partner,
+                self.draft.get_deal()
+            ),
+        };
+        let editor = Table::new(
+            content,
+            [Constraint::Percentage(30), Constraint::Percentage(70)].iter(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from(format!(
+                    "{} - left / right: change, Enter: send",
+                    title
+                ))),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(area);
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(columns[0]);
+        // Only the focused table shows its selection.
+        let mut unfocused = TableState::default();
+        let (list_state, editor_state) = if self.editor_focused {
+            (&mut unfocused, self.field_selected.get_mut())
+        } else {
+            (self.selected.get_mut(), &mut unfocused)
+        };
+        f.render_stateful_widget(offers, left[0], list_state);
+        f.render_widget(contracts, left[1]);
+        f.render_stateful_widget(editor, columns[1], editor_state);
+    }
+
+    fn handle_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        if let InputAction::SwitchFocus = input {
+            self.editor_focused = !self.editor_focused;
+            return None;
+        }
+        if self.editor_focused {
+            let field = enum_iterator::all::<DealField>().nth(self.field_selected.get_row())?;
+            let received: Vec<_> = self
+                .offers
+                .iter()
+                .filter(|(_, from)| *from != player)
+                .map(|(id, _)| *id)
+                .collect();
+            return match input {
+                InputAction::MoveUp => {
+                    self.field_selected.prev();
+                    None
+                }
+                InputAction::MoveDown => {
+                    self.field_selected.next();
+                    None
+                }
+                InputAction::Decrease | InputAction::Increase => {
+                    let forward = matches!(input, InputAction::Increase);
+                    self.draft
+                        .change(field, forward, player, self.players, &received);
+                    None
+                }
+                InputAction::PerformAction => Some(self.draft.get_action(player)),
+                _ => None,
+            };
+        }
+        let (id, from) = *self.offers.get(self.selected.get_row())?;
+        match input {
+            InputAction::MoveUp => {
+                self.selected.prev();
+                None
+            }
+            InputAction::MoveDown => {
+                self.selected.next();
+                None
+            }
+            InputAction::PerformAction if from != player => {
+                Some(GameAction::AcceptDeal(player, id))
+            }
+            InputAction::CancelItem => Some(GameAction::DeclineDeal(player, id)),
+            _ => None,
+        }
+    }
+}
+
+/// The rows of the editor of the Bank tab.
+#[derive(Clone, Copy, Debug, PartialEq, Sequence)]
+enum BankField {
+    Borrow,
+    Periods,
+    Deposit,
+    Withdraw,
+}
+
+impl BankField {
+    fn get_label(&self) -> &'static str {
+        match self {
+            BankField::Borrow => "Borrow",
+            BankField::Periods => "Over periods",
+            BankField::Deposit => "Deposit",
+            BankField::Withdraw => "Withdraw",
+        }
+    }
+}
+
+/// Converts an interest rate in billionths per tick into percent per minute.
+fn percent_per_minute(rate: u64) -> f64 {
+    (rate * 60 * Duration::TICKS_PER_SEC) as f64 * 100.0 / RATE_SCALE as f64
+}
+
+struct BankTab {
+    selected: WrappingTableState,
+    field_selected: WrappingTableState,
+    /// Whether the arrow keys work on the editor rather than on the list of loans.
+    editor_focused: bool,
+    /// The id and balance of every loan listed, and the player's money, as last drawn.
+    loans: Vec<(LoanId, u64)>,
+    money: u64,
+    borrow: u64,
+    periods: u32,
+    deposit: u64,
+    withdraw: u64,
+}
+
+impl Default for BankTab {
+    fn default() -> Self {
+        BankTab {
+            selected: WrappingTableState::new(0, 0),
+            field_selected: WrappingTableState::new(0, enum_iterator::cardinality::<BankField>()),
+            editor_focused: false,
+            loans: Vec::new(),
+            money: 0,
+            borrow: 1000,
+            periods: 10,
+            deposit: 500,
+            withdraw: 500,
+        }
+    }
+}
+
+impl BankTab {
+    fn get_value(&self, field: BankField) -> String {
+        match field {
+            BankField::Borrow => self.borrow.to_string(),
+            BankField::Periods => self.periods.to_string(),
+            BankField::Deposit => self.deposit.to_string(),
+            BankField::Withdraw => self.withdraw.to_string(),
+        }
+    }
+
+    fn change(&mut self, field: BankField, forward: bool) {
+        let add = |value: u64, step: u64| {
+            if forward {
+                value + step
+            } else {
+                value.saturating_sub(step)
+            }
+        };
+        match field {
+            BankField::Borrow => self.borrow = add(self.borrow, 100),
+            BankField::Periods => {
+                let max = content::get().get_bank().max_periods;
+                self.periods = (add(self.periods as u64, 1) as u32).clamp(1, max);
+            }
+            BankField::Deposit => self.deposit = add(self.deposit, 100),
+            BankField::Withdraw => self.withdraw = add(self.withdraw, 100),
+        }
+    }
+}
+
+impl Tab for BankTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, player: PlayerId, state: &GameState) {
+        let p = state.get_player(player);
+        let account = p.get_account();
+        let bank = content::get().get_bank();
+        self.loans = account
+            .get_loans()
+            .map(|loan| (loan.id, loan.balance))
+            .collect();
+        self.money = p.get_money();
+        self.selected.resize(self.loans.len());
+
+        let locked = account.is_locked(state.get_tick());
+        let summary_rows = [
+            ("Money".to_owned(), p.get_money().to_string()),
+            (
+                format!(
+                    "Deposit at {:.2}%/min",
+                    percent_per_minute(bank.deposit_interest)
+                ),
+                account.get_deposit().to_string(),
+            ),
+            (
+                format!("Debt at {:.2}%/min", percent_per_minute(bank.loan_interest)),
+                account.get_debt().to_string(),
+            ),
+            ("Net worth".to_owned(), p.get_net_worth().to_string()),
+            (
+                "Credit available".to_owned(),
+                if locked {
+                    "none after bankruptcy".to_owned()
+                } else {
+                    p.get_credit_available(state.get_tick()).to_string()
+                },
+            ),
+            (
+                "Bankruptcies".to_owned(),
+                account.get_bankruptcies().to_string(),
+            ),
+        ]
+        .map(|(label, value)| Row::new(vec![Cell::from(label), Cell::from(value)]));
+        let summary = Table::new(
+            summary_rows,
+            [Constraint::Percentage(50), Constraint::Percentage(50)].iter(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Account")),
+        );
+
+        let header = Row::new(vec!["#", "Balance", "Installment", "Left", "Missed"]);
+        let content = account.get_loans().map(|loan| {
+            let style = if loan.missed > 0 {
+                Style::default().fg(Color::LightRed)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(loan.id.to_string()),
+                Cell::from(loan.balance.to_string()),
+                Cell::from(loan.get_installment().to_string()),
+                Cell::from(loan.periods_left.to_string()),
+                Cell::from(format!("{}/{}", loan.missed, bank.default_after)),
+            ])
+            .style(style)
+        });
+        let loans = Table::new(
+            content,
+            [
+                Constraint::Percentage(10),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+            ]
+            .iter(),
+        )
+        .header(header)
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Loans - Enter: repay")),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
+
+        let content = enum_iterator::all::<BankField>().map(|field| {
+            Row::new(vec![
+                Cell::from(field.get_label()),
+                Cell::from(self.get_value(field)),
+            ])
+        });
+        let editor = Table::new(
+            content,
+            [Constraint::Percentage(40), Constraint::Percentage(60)].iter(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .style(Style::default().bg(Color::DarkGray))
+                .title(Span::from("Bank - left / right: change, Enter: go")),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">>");
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(area);
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(8), Constraint::Min(0)].as_ref())
+            .split(columns[0]);
+        // Only the focused table shows its selection.
+        let mut unfocused = TableState::default();
+        let (list_state, editor_state) = if self.editor_focused {
+            (&mut unfocused, self.field_selected.get_mut())
+        } else {
+            (self.selected.get_mut(), &mut unfocused)
+        };
+        f.render_widget(summary, left[0]);
+        f.render_stateful_widget(loans, left[1], list_state);
+        f.render_stateful_widget(editor, columns[1], editor_state);
+    }
+
+    fn handle_input(&mut self, player: PlayerId, input: InputAction) -> Option<GameAction> {
+        if let InputAction::SwitchFocus = input {
+            self.editor_focused = !self.editor_focused;
+            return None;
+        }
+        if self.editor_focused {
+            let field = enum_iterator::all::<BankField>().nth(self.field_selected.get_row())?;
+            return match input {
+                InputAction::MoveUp => {
+                    self.field_selected.prev();
+                    None
+                }
+                InputAction::MoveDown => {
+                    self.field_selected.next();
+                    None
+                }
+                InputAction::Decrease | InputAction::Increase => {
+                    self.change(field, matches!(input, InputAction::Increase));
+                    None
+                }
+                InputAction::PerformAction => Some(match field {
+                    BankField::Borrow | BankField::Periods => {
+                        GameAction::TakeLoan(player, self.borrow, self.periods)
+                    }
+                    BankField::Deposit => GameAction::DepositMoney(player, self.deposit),
+                    BankField::Withdraw => GameAction::WithdrawMoney(player, self.withdraw),
+                }),
+                _ => None,
+            };
+        }
+        let (id, balance) = *self.loans.get(self.selected.get_row())?;
+        match input {
+            InputAction::MoveUp => {
+                self.selected.prev();
+                None
+            }
+            InputAction::MoveDown => {
+                self.selected.next();
+                None
+            }
+            // Pays off as much of the loan as the player's money covers.
+            InputAction::PerformAction => {
+                Some(GameAction::RepayLoan(player, id, balance.min(self.money)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScriptTab {
+    /// The output of the bot script, or `None` if no script is running.
+    log: Option<Vec<String>>,
+}
+
+impl Tab for ScriptTab {
+    fn draw(&mut self, f: &mut Frame, area: Rect, _: PlayerId, _: &GameState) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .style(Style::default().bg(Color::DarkGray))
+            .title(Span::from("Bot script"));
+        let Some(log) = &self.log else {
+            let hint =
+                Paragraph::new("No script is running. Start the game with --bot-script <path>.")
+                    .block(block);
+            f.render_widget(hint, area);
+            return;
+        };
+        // Only the newest lines that fit are shown.
+        let shown = area.height.saturating_sub(2) as usize;
+        let content = log
+            .iter()
+            .skip(log.len().saturating_sub(shown))
+            .map(|line| {
+                let style = if line.contains(": error at line ") || line.contains(" failed: ") {
+                    Style::default().fg(Color::LightRed)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                Row::new(vec![Cell::from(line.as_str())]).style(style)
+            });
+        let table = Table::new(content, [Constraint::Percentage(100)].iter()).block(block);
+        f.render_widget(table, area);
+    }
+
+    fn handle_input(&mut self, _: PlayerId, _: InputAction) -> Option<GameAction> {
+        None
+    }
+}
+
 fn draw_tabs(f: &mut Frame, area: Rect, _: PlayerId, sel: TabType) {
     let tab_bar = Layout::default()
         .direction(Direction::Horizontal)
@@ -390,13 +1912,32 @@ fn draw_tabs(f: &mut Frame, area: Rect, _: PlayerId, sel: TabType) {
     f.render_widget(tabs, tab_bar[0]);
 }
 
-fn draw_status(f: &mut Frame, area: Rect, _: PlayerId, state: &GameState) {
+fn draw_status(
+    f: &mut Frame,
+    area: Rect,
+    _: PlayerId,
+    state: &GameState,
+    speed: GameSpeed,
+    message: Option<&String>,
+) {
     let exec_status = if state.is_paused() {
         "Paused"
     } else {
         "Running"
     };
-    let exec_status_box = Paragraph::new(exec_status).block(Block::default().borders(Borders::ALL));
+    let mut status = format!(
+        "{} | Speed: {} | Tick: {} | Mode: {} | Seed: {}",
+        exec_status,
+        speed,
+        state.get_tick(),
+        state.get_policy().mode,
+        state.get_seed()
+    );
+    if let Some(message) = message {
+        status += " | ";
+        status += message;
+    }
+    let exec_status_box = Paragraph::new(status).block(Block::default().borders(Borders::ALL));
     f.render_widget(exec_status_box, area);
 }
 
@@ -407,6 +1948,15 @@ pub struct Visualization<B: Backend> {
     help_tab: HelpTab,
     prod_tab: ProductionTab,
     sell_tab: SellTab,
+    market_tab: MarketTab,
+    buy_tab: BuyTab,
+    buildings_tab: BuildingsTab,
+    automation_tab: AutomationTab,
+    deals_tab: DealsTab,
+    bank_tab: BankTab,
+    script_tab: ScriptTab,
+    speed: GameSpeed,
+    message: Option<String>,
 }
 
 impl<B: Backend> Visualization<B> {
@@ -418,10 +1968,33 @@ impl<B: Backend> Visualization<B> {
             help_tab: HelpTab::default(),
             prod_tab: ProductionTab::default(),
             sell_tab: SellTab::default(),
+            market_tab: MarketTab::default(),
+            buy_tab: BuyTab::default(),
+            buildings_tab: BuildingsTab::default(),
+            automation_tab: AutomationTab::default(),
+            deals_tab: DealsTab::default(),
+            bank_tab: BankTab::default(),
+            script_tab: ScriptTab::default(),
+            speed: GameSpeed::default(),
+            message: None,
         }
     }
 
-    pub fn draw(&mut self, player: PlayerId, state: &mut GameState) {
+    pub fn set_speed(&mut self, speed: GameSpeed) {
+        self.speed = speed;
+    }
+
+    /// Shows `message` in the status bar until it is replaced.
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    /// Shows `log` as the output of the bot script on the Script tab.
+    pub fn set_script_log(&mut self, log: Vec<String>) {
+        self.script_tab.log = Some(log);
+    }
+
+    pub fn draw(&mut self, player: PlayerId, state: &GameState) {
         let Visualization::<B> {
             term: ref mut t,
             tab: ref mut sel_tab,
@@ -429,6 +2002,15 @@ impl<B: Backend> Visualization<B> {
             help_tab: ref mut h_tab,
             prod_tab: ref mut p_tab,
             sell_tab: ref mut s_tab,
+            market_tab: ref mut m_tab,
+            buy_tab: ref mut b_tab,
+            buildings_tab: ref mut u_tab,
+            automation_tab: ref mut a_tab,
+            deals_tab: ref mut o_tab,
+            bank_tab: ref mut k_tab,
+            script_tab: ref mut t_tab,
+            speed,
+            message: ref msg,
         } = self;
         t.draw(|f| {
             let rects = Layout::default()
@@ -449,8 +2031,15 @@ impl<B: Backend> Visualization<B> {
                 TabType::Help => h_tab.draw(f, rects[1], player, state),
                 TabType::Production => p_tab.draw(f, rects[1], player, state),
                 TabType::Sell => s_tab.draw(f, rects[1], player, state),
+                TabType::Market => m_tab.draw(f, rects[1], player, state),
+                TabType::Buy => b_tab.draw(f, rects[1], player, state),
+                TabType::Buildings => u_tab.draw(f, rects[1], player, state),
+                TabType::Automation => a_tab.draw(f, rects[1], player, state),
+                TabType::Deals => o_tab.draw(f, rects[1], player, state),
+                TabType::Bank => k_tab.draw(f, rects[1], player, state),
+                TabType::Script => t_tab.draw(f, rects[1], player, state),
             }
-            draw_status(f, rects[2], player, state);
+            draw_status(f, rects[2], player, state, *speed, msg.as_ref());
         })
         .unwrap();
     }
@@ -462,6 +2051,13 @@ impl<B: Backend> Visualization<B> {
             help_tab: ref mut h_tab,
             prod_tab: ref mut p_tab,
             sell_tab: ref mut s_tab,
+            market_tab: ref mut m_tab,
+            buy_tab: ref mut b_tab,
+            buildings_tab: ref mut u_tab,
+            automation_tab: ref mut a_tab,
+            deals_tab: ref mut o_tab,
+            bank_tab: ref mut k_tab,
+            script_tab: ref mut t_tab,
             ..
         } = self;
         match input {
@@ -475,6 +2071,13 @@ impl<B: Backend> Visualization<B> {
                 TabType::Help => h_tab.handle_input(player, i),
                 TabType::Production => p_tab.handle_input(player, i),
                 TabType::Sell => s_tab.handle_input(player, i),
+                TabType::Market => m_tab.handle_input(player, i),
+                TabType::Buy => b_tab.handle_input(player, i),
+                TabType::Buildings => u_tab.handle_input(player, i),
+                TabType::Automation => a_tab.handle_input(player, i),
+                TabType::Deals => o_tab.handle_input(player, i),
+                TabType::Bank => k_tab.handle_input(player, i),
+                TabType::Script => t_tab.handle_input(player, i),
             },
         }
     }